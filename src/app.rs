@@ -1,22 +1,54 @@
+use std::collections::HashMap;
 use std::io;
+use std::time::{Duration, Instant};
 
-use arboard::Clipboard;
-
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::widgets::*;
 
-use crate::kvs::Kvs;
+use crate::audit::{audit, Finding};
+use crate::clipboard::SecretClipboard;
+use crate::config::Config;
+use crate::editor::{EditAction, KillRing, LineEditor};
+use crate::entry::{parse_tags, Field, HistoryItem, Kind, VALUE_FIELD};
+use crate::fuzzy::{fuzzy_match, recency_bonus, sort_matches, Match, Query, TAG_PREFIX};
+use crate::generator;
+use crate::highlight::next_language;
+use crate::kvs::{Kvs, KvsError};
+use crate::totp::Totp;
 use crate::ui::ui;
+use crate::vault::Vault;
+
+/// how long to wait for a key press before checking the idle timer
+const TICK_RATE: Duration = Duration::from_millis(250);
+/// lines scrolled by PageUp/PageDown in the value viewer
+const VIEWER_PAGE: usize = 10;
 
 pub enum InputMode {
+    Locked,
     Home,
     Search,
     Select,
+    History,
     Edit,
     Delete,
+    Rename,
+    RenameConfirm,
     AddKey,
+    /// the key typed in add mode already exists
+    AddExists,
     AddValue,
+    Vault,
+    NewVault,
+    Audit,
+    /// field picker of the selected entry
+    Fields,
+    /// name of a field to add
+    FieldName,
+    FieldValue,
+    /// tag sidebar of search mode
+    Tags,
+    EditTags,
 }
 
 #[derive(Debug, Clone)]
@@ -34,6 +66,9 @@ impl<T> StatefulList<T> {
     }
 
     fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -48,6 +83,9 @@ impl<T> StatefulList<T> {
     }
 
     fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -65,148 +103,668 @@ impl<T> StatefulList<T> {
 /// holds the state of the application
 pub struct App {
     /// search input in search mode
-    pub search_input: String,
+    pub search_input: LineEditor,
     /// list of stored keys
     pub key_list: Vec<String>,
-    /// list of search target keys
-    pub stateful_key_list: StatefulList<String>,
+    /// when each key was last used, to rank recent keys higher
+    pub last_used: HashMap<String, u64>,
+    /// tags of each tagged key
+    pub key_tags: HashMap<String, Vec<String>>,
+    /// every tag with the number of keys that have it, in the sidebar of search mode
+    pub tag_list: StatefulList<(String, usize)>,
+    /// list of search target keys, best match first
+    pub stateful_key_list: StatefulList<Match>,
+    /// previous values of the selected key in history mode
+    pub history_list: StatefulList<HistoryItem>,
+    /// problems found in audit mode
+    pub audit_list: StatefulList<Finding>,
+    /// field names of the selected entry in field mode, the main value first
+    pub field_list: StatefulList<String>,
+    /// field whose value is in the value input
+    pub editing_field: Option<String>,
     /// key input in add mode
-    pub key_input: String,
+    pub key_input: LineEditor,
     /// value input in add mode
-    pub value_input: String,
-    /// cursor position in the input
-    pub cursor_position: usize,
+    pub value_input: LineEditor,
+    /// first line and column shown by the value input
+    pub value_scroll: (u16, u16),
+    /// language tag saved with the value input, `None` to detect it
+    pub language: Option<String>,
+    /// replacing the value of an existing key was confirmed in add mode
+    pub overwrite: bool,
+    /// mask the value input unless revealed
+    pub value_sensitive: bool,
+    /// when values were revealed; they are masked again after the reveal timeout
+    pub revealed_at: Option<Instant>,
+    /// first line shown by the value viewer in select mode
+    pub viewer_scroll: usize,
     /// current mode of the app
     pub mode: InputMode,
+    /// master password input in locked mode
+    pub password_input: LineEditor,
+    /// first entry of a new master password, waiting for confirmation
+    pub new_password: Option<String>,
+    /// message shown on the lock screen
+    pub lock_message: Option<String>,
+    /// time of the last key press
+    pub last_activity: Instant,
+    /// error from the last action, shown until the next key press
+    pub error_message: Option<String>,
+    /// size of the last paste or strength of a generated value, shown until the next key press
+    pub info_message: Option<String>,
+    /// clipboard holding the last copied value until it is cleared
+    pub clipboard: SecretClipboard,
+    /// user settings
+    pub config: Config,
+    /// the vault `kvs` was opened from
+    pub vault: Vault,
+    /// vaults on disk in vault mode
+    pub vault_list: StatefulList<String>,
+    /// name input when creating a vault
+    pub vault_input: LineEditor,
+    /// text removed with Ctrl-k/u/w, shared by the inputs
+    pub kill_ring: KillRing,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(config: Config, vault: Vault) -> Self {
         Self {
-            search_input: String::new(),
-            key_input: String::new(),
+            search_input: LineEditor::new(),
+            key_input: LineEditor::new(),
             key_list: vec![],
+            last_used: HashMap::new(),
             stateful_key_list: StatefulList::with_items(vec![]),
-            value_input: String::new(),
-            cursor_position: 0,
-            mode: InputMode::Home,
+            history_list: StatefulList::with_items(vec![]),
+            audit_list: StatefulList::with_items(vec![]),
+            key_tags: HashMap::new(),
+            tag_list: StatefulList::with_items(vec![]),
+            field_list: StatefulList::with_items(vec![]),
+            editing_field: None,
+            value_input: LineEditor::new(),
+            value_scroll: (0, 0),
+            language: None,
+            overwrite: false,
+            value_sensitive: true,
+            revealed_at: None,
+            viewer_scroll: 0,
+            mode: InputMode::Locked,
+            password_input: LineEditor::new(),
+            new_password: None,
+            lock_message: None,
+            last_activity: Instant::now(),
+            error_message: None,
+            info_message: None,
+            clipboard: SecretClipboard::new(config.clipboard, config.clipboard_timeout()),
+            config,
+            vault,
+            vault_list: StatefulList::with_items(vec![]),
+            vault_input: LineEditor::new(),
+            kill_ring: KillRing::default(),
+        }
+    }
+
+    fn unlock(&mut self, kvs: &mut Kvs) -> Result<(), KvsError> {
+        let password = self.password_input.take();
+        if !kvs.is_initialized()? {
+            // a new vault asks for the password twice
+            match self.new_password.take() {
+                None => {
+                    self.new_password = Some(password);
+                    self.lock_message = Some("confirm the new master password".to_string());
+                    return Ok(());
+                }
+                Some(first) if first != password => {
+                    self.lock_message = Some("passwords do not match".to_string());
+                    return Ok(());
+                }
+                Some(_) => {}
+            }
+        }
+        match kvs.unlock(&password) {
+            Ok(()) => {
+                self.lock_message = None;
+                self.sync_key_list(kvs)?;
+                self.mode = InputMode::Home;
+            }
+            Err(KvsError::WrongPassword) => {
+                self.lock_message = Some(KvsError::WrongPassword.to_string());
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// drop the vault key and everything read from the vault
+    fn lock(&mut self, kvs: &mut Kvs) {
+        kvs.lock();
+        self.clipboard.clear_now();
+        self.search_input.clear();
+        self.key_input.clear();
+        self.value_input.clear();
+        self.language = None;
+        self.overwrite = false;
+        self.revealed_at = None;
+        self.password_input.clear();
+        self.kill_ring.clear();
+        self.new_password = None;
+        self.key_list.clear();
+        self.last_used.clear();
+        self.key_tags.clear();
+        self.tag_list = StatefulList::with_items(vec![]);
+        self.stateful_key_list = StatefulList::with_items(vec![]);
+        self.history_list = StatefulList::with_items(vec![]);
+        self.audit_list = StatefulList::with_items(vec![]);
+        self.field_list = StatefulList::with_items(vec![]);
+        self.editing_field = None;
+        self.lock_message = None;
+        self.mode = InputMode::Locked;
+    }
+
+    /// list the vaults on disk, starting at the current one
+    fn show_vaults(&mut self) -> Result<(), KvsError> {
+        let names = Vault::list().map_err(KvsError::Io)?;
+        let current = names.iter().position(|name| *name == self.vault.name);
+        self.vault_list = StatefulList::with_items(names);
+        self.vault_list.state.select(current.or(Some(0)));
+        self.mode = InputMode::Vault;
+        Ok(())
+    }
+
+    /// lock the current vault and open `vault` in its place, creating it if `create` is set
+    fn switch_vault(&mut self, kvs: &mut Kvs, vault: Vault, create: bool) -> Result<(), KvsError> {
+        if vault.path == self.vault.path {
+            self.leave_vaults(kvs);
+            return Ok(());
+        }
+        // open first, so that a vault in use elsewhere leaves the current one untouched
+        let opened = match create {
+            true => vault.create()?,
+            false => vault.open()?,
+        };
+        kvs.flush()?;
+        self.lock(kvs);
+        *kvs = opened;
+        self.vault = vault;
+        Ok(())
+    }
+
+    fn leave_vaults(&mut self, kvs: &Kvs) {
+        self.mode = if kvs.is_unlocked() {
+            InputMode::Home
+        } else {
+            InputMode::Locked
+        };
+    }
+
+    /// whether masked values are shown
+    pub fn is_revealed(&self) -> bool {
+        match (self.revealed_at, self.config.reveal_timeout()) {
+            (Some(revealed_at), Some(timeout)) => revealed_at.elapsed() < timeout,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// time until revealed values are masked again
+    pub fn reveal_remaining(&self) -> Option<Duration> {
+        let timeout = self.config.reveal_timeout()?;
+        let elapsed = self.revealed_at?.elapsed();
+        timeout.checked_sub(elapsed).filter(|d| !d.is_zero())
+    }
+
+    fn toggle_reveal(&mut self) {
+        self.revealed_at = match self.is_revealed() {
+            true => None,
+            false => Some(Instant::now()),
+        };
+    }
+
+    /// mask values again; a reveal only holds for the key, or the input, it was made on
+    fn hide_values(&mut self) {
+        self.revealed_at = None;
+    }
+
+    fn select_next(&mut self) {
+        self.stateful_key_list.next();
+        self.viewer_scroll = 0;
+        self.hide_values();
+    }
+
+    fn select_previous(&mut self) {
+        self.stateful_key_list.previous();
+        self.viewer_scroll = 0;
+        self.hide_values();
+    }
+
+    fn is_idle(&self) -> bool {
+        match self.config.lock_timeout() {
+            Some(timeout) => self.last_activity.elapsed() >= timeout,
+            None => false,
+        }
+    }
+
+    /// lock once nothing was typed for the configured timeout
+    fn lock_if_idle(&mut self, kvs: &mut Kvs) {
+        if !matches!(self.mode, InputMode::Locked) && self.is_idle() {
+            self.lock(kvs);
+            self.lock_message = Some("locked after inactivity".to_string());
         }
     }
 
-    pub fn get_search_key_list(&mut self) -> Vec<String> {
-        let search_key_list: Vec<String> = self
+    /// rank the keys against the search input; the selected key stays selected while it matches
+    pub fn refilter(&mut self) {
+        let selected_key = self.get_selected_key();
+        let query = Query::parse(self.search_input.as_str());
+        let mut search_key_list: Vec<Match> = self
             .key_list
             .iter()
-            .filter(|key| {
-                let key_lower = key.to_lowercase();
-                let search_input_lower = self.search_input.to_lowercase();
-                key_lower.contains(&search_input_lower)
+            .filter(|key| query.has_tags(self.key_tags.get(*key).map_or(&[], Vec::as_slice)))
+            .filter_map(|key| {
+                let mut key_match = fuzzy_match(&query.text, key)?;
+                key_match.score += recency_bonus(self.last_used.get(key).copied());
+                Some(key_match)
             })
-            .map(|key| key.to_owned())
             .collect();
-        self.stateful_key_list = StatefulList::with_items(search_key_list.clone()); // required to
-                                                                                    // initialize
-                                                                                    // stateful_key_list
-        search_key_list
+        sort_matches(&mut search_key_list);
+        let selected = match selected_key
+            .as_ref()
+            .and_then(|key| search_key_list.iter().position(|m| m.text == *key))
+        {
+            Some(i) => Some(i),
+            None if search_key_list.is_empty() => None,
+            None => Some(0),
+        };
+        self.stateful_key_list = StatefulList::with_items(search_key_list);
+        self.stateful_key_list.state.select(selected);
+        if self.get_selected_key() != selected_key {
+            self.hide_values();
+        }
     }
 
-    pub fn get_statefule_list(&mut self) -> StatefulList<String> {
+    pub fn get_statefule_list(&mut self) -> StatefulList<Match> {
         self.stateful_key_list.to_owned()
     }
 
-    pub fn get_mut_stateful_key_list(&mut self) -> &mut StatefulList<String> {
+    pub fn get_mut_stateful_key_list(&mut self) -> &mut StatefulList<Match> {
         &mut self.stateful_key_list
     }
 
-    pub fn sync_key_list(&mut self, key_list: Vec<String>) {
-        self.key_list = key_list;
+    /// reload the keys, when they were last used and their tags
+    pub fn sync_key_list(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        self.key_list = kvs.get_key_vec()?;
+        self.last_used.clear();
+        self.key_tags.clear();
+        // an unreadable entry still shows up in the list, just without recency or tags
+        for key in &self.key_list {
+            let Ok(entry) = kvs.get_entry(key) else {
+                continue;
+            };
+            if let Some(last_accessed) = entry.last_accessed {
+                self.last_used.insert(key.clone(), last_accessed);
+            }
+            if !entry.tags.is_empty() {
+                self.key_tags.insert(key.clone(), entry.tags);
+            }
+        }
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for tag in self.key_tags.values().flatten() {
+            *counts.entry(tag).or_default() += 1;
+        }
+        let mut tags = counts
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect::<Vec<_>>();
+        tags.sort();
+        let selected = self.tag_list.state.selected().unwrap_or(0);
+        self.tag_list = StatefulList::with_items(tags);
+        if !self.tag_list.items.is_empty() {
+            let selected = selected.min(self.tag_list.items.len() - 1);
+            self.tag_list.state.select(Some(selected));
+        }
+        self.refilter();
+        Ok(())
+    }
+
+    /// add `tag:<tag>` to the search input, or remove it if it is already there
+    fn toggle_tag_filter(&mut self, tag: &str) {
+        let filter = format!("{}{}", TAG_PREFIX, tag);
+        let mut words = self
+            .search_input
+            .as_str()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        let before = words.len();
+        words.retain(|word| !word.eq_ignore_ascii_case(&filter));
+        if words.len() == before {
+            words.insert(0, filter);
+        }
+        let mut input = words.join(" ");
+        // leave room to keep typing the text part
+        if !input.is_empty() {
+            input.push(' ');
+        }
+        self.search_input.set(&input);
+        self.refilter();
+    }
+
+    /// the tag filters in the search input
+    pub fn active_tags(&self) -> Vec<String> {
+        Query::parse(self.search_input.as_str()).tags
     }
 
     pub fn get_selected_key(&self) -> Option<String> {
-        match self.stateful_key_list.state.selected() {
-            Some(i) => Some(self.stateful_key_list.items[i].to_owned()),
-            None => None,
+        self.stateful_key_list
+            .state
+            .selected()
+            .map(|i| self.stateful_key_list.items[i].text.to_owned())
+    }
+
+    /// copy the value of the selected key to the clipboard
+    fn copy_selected(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        self.copy_field(kvs, VALUE_FIELD)
+    }
+
+    /// copy one field of the selected entry
+    fn copy_field(&mut self, kvs: &Kvs, field: &str) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let value = match kvs.get_entry(&key)?.copy_text(field) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.error_message = Some(e);
+                    return Ok(());
+                }
+            };
+            match self.clipboard.copy(value) {
+                Ok(()) => {
+                    kvs.touch(&key)?;
+                    self.sync_key_list(kvs)?;
+                }
+                Err(e) => self.error_message = Some(format!("cannot copy to clipboard: {}", e)),
+            }
         }
+        Ok(())
     }
 
-    fn remove_from_kvs(&mut self, kvs: &Kvs) {
+    fn remove_from_kvs(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
         if let Some(key) = self.get_selected_key() {
-            kvs.delete(key.as_str());
-            self.sync_key_list(kvs.get_key_vec());
+            kvs.delete(key.as_str())?;
+            self.sync_key_list(kvs)?;
         }
+        Ok(())
     }
 
-    fn add_to_kvs(&mut self, kvs: &Kvs) {
-        if !self.key_input.is_empty() && !self.value_input.is_empty() {
-            kvs.insert(&self.key_input, &self.value_input);
+    /// move the selected key to the key input, keeping it selected
+    fn rename_selected(&mut self, kvs: &Kvs, overwrite: bool) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let new_key = self.key_input.as_str().to_string();
+            kvs.rename(&key, &new_key, overwrite)?;
+            self.sync_key_list(kvs)?;
+            self.select_key(&new_key);
+            self.key_input.clear();
+            self.mode = InputMode::Select;
+        }
+        Ok(())
+    }
 
-            self.sync_key_list(kvs.get_key_vec());
+    /// select `key`, clearing the search filter if it hides the key
+    fn select_key(&mut self, key: &str) {
+        let position = |list: &StatefulList<Match>| list.items.iter().position(|m| m.text == key);
+        if position(&self.stateful_key_list).is_none() {
+            self.search_input.clear();
+            self.refilter();
+        }
+        let selected = position(&self.stateful_key_list);
+        if selected != self.stateful_key_list.state.selected() {
+            self.hide_values();
+        }
+        self.stateful_key_list.state.select(selected);
+    }
 
-            self.key_input.clear();
-            self.value_input.clear();
-            self.mode = InputMode::Home;
+    fn edit_tags(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let tags = kvs.get_entry(&key)?.tags;
+            self.key_input.set(&tags.join(", "));
+            self.mode = InputMode::EditTags;
         }
+        Ok(())
     }
 
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.cursor_position.saturating_add(1);
-        self.cursor_position = self.clamp_cursor_position(cursor_moved_right);
+    fn save_tags(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let tags = parse_tags(&self.key_input.take());
+            kvs.update_entry(&key, |entry| entry.set_tags(tags))?;
+            self.sync_key_list(kvs)?;
+            // the key stays selected even if it no longer has a tag being filtered by
+            self.select_key(&key);
+        }
+        self.mode = InputMode::Select;
+        Ok(())
     }
 
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.cursor_position.saturating_sub(1);
-        self.cursor_position = self.clamp_cursor_position(cursor_moved_left);
+    /// switch an entry between a plain secret and a TOTP secret, if it is one
+    fn toggle_totp(&mut self, kvs: &Kvs, key: &str) -> Result<(), KvsError> {
+        let entry = kvs.get_entry(key)?;
+        let kind = match entry.kind {
+            Kind::Totp => Kind::Secret,
+            Kind::Secret => Kind::Totp,
+        };
+        if kind == Kind::Totp {
+            if let Err(e) = Totp::parse(&entry.value) {
+                self.error_message = Some(e);
+                return Ok(());
+            }
+        }
+        kvs.update_entry(key, |entry| entry.kind = kind)
     }
 
-    fn clamp_cursor_position(&self, new_cursor_position: usize) -> usize {
+    /// list the fields of the selected entry, selecting `field` if given
+    fn show_fields(&mut self, kvs: &Kvs, field: Option<&str>) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let names = kvs.get_entry(&key)?.field_names();
+            let selected = field
+                .and_then(|field| names.iter().position(|name| name == field))
+                .unwrap_or(0);
+            self.field_list = StatefulList::with_items(names);
+            self.field_list.state.select(Some(selected));
+            self.mode = InputMode::Fields;
+        }
+        Ok(())
+    }
+
+    fn selected_field(&self) -> Option<String> {
+        let i = self.field_list.state.selected()?;
+        self.field_list.items.get(i).cloned()
+    }
+
+    /// edit a field of the selected entry, which may not exist yet; the main value goes to edit mode
+    fn edit_field(&mut self, kvs: &Kvs, field: String) -> Result<(), KvsError> {
+        if field == VALUE_FIELD {
+            return self.edit_selected(kvs);
+        }
+        if let Some(key) = self.get_selected_key() {
+            let entry = kvs.get_entry(&key)?;
+            self.value_input
+                .set(entry.field(&field).unwrap_or_default());
+            self.value_sensitive = entry.sensitive && !Field::is_public(&field);
+            self.language = None;
+            self.value_scroll = (0, 0);
+            self.editing_field = Some(field);
+            self.hide_values();
+            self.mode = InputMode::FieldValue;
+        }
+        Ok(())
+    }
+
+    /// store the value input in the edited field; an empty value removes the field
+    fn save_field(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        if let (Some(key), Some(field)) = (self.get_selected_key(), self.editing_field.take()) {
+            kvs.set_field(&key, &field, &self.value_input.take())?;
+            self.show_fields(kvs, Some(&field))?;
+        }
+        Ok(())
+    }
+
+    /// check every value and list the findings
+    fn show_audit(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        let findings = audit(&kvs.get_entries()?, self.config.audit_max_age_days);
+        let selected = (!findings.is_empty()).then_some(0);
+        self.audit_list = StatefulList::with_items(findings);
+        self.audit_list.state.select(selected);
+        self.mode = InputMode::Audit;
+        Ok(())
+    }
+
+    /// open the selected entry in edit mode
+    fn edit_selected(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        if let Some(key) = self.get_selected_key() {
+            let entry = kvs.get_entry(key.as_str())?;
+            self.value_input.set(&entry.value);
+            self.language = entry.language;
+            self.value_sensitive = entry.sensitive;
+            self.value_scroll = (0, 0);
+            self.hide_values();
+            self.mode = InputMode::Edit;
+        }
+        Ok(())
+    }
+
+    /// in add mode, ask before replacing the value of a key that exists (or was added meanwhile).
+    /// a broken otpauth URI is refused with an error message
+    fn add_to_kvs(&mut self, kvs: &Kvs) -> Result<(), KvsError> {
+        if !self.key_input.is_empty() && !self.value_input.is_empty() {
+            let is_totp = self.value_input.as_str().starts_with("otpauth://totp/");
+            if is_totp {
+                // an otpauth URI that does not parse would be stored as a broken TOTP entry
+                if let Err(e) = Totp::parse(self.value_input.as_str()) {
+                    self.error_message = Some(e);
+                    return Ok(());
+                }
+            }
+            if matches!(self.mode, InputMode::AddValue)
+                && !self.overwrite
+                && kvs.contains(self.key_input.as_str())?
+            {
+                self.mode = InputMode::AddExists;
+                return Ok(());
+            }
+            let value = self.value_input.as_str();
+            let language = self.language.clone();
+            kvs.insert_with(self.key_input.as_str(), value, |entry| {
+                entry.language = language;
+                if is_totp {
+                    entry.kind = Kind::Totp;
+                }
+            })?;
+
+            self.sync_key_list(kvs)?;
+
+            self.key_input.clear();
+            self.value_input.clear();
+            self.language = None;
+            self.overwrite = false;
+            self.mode = InputMode::Home;
+        }
+        Ok(())
+    }
+
+    /// column of the cursor in the current input; the masked password shows one '*' per character
+    pub fn cursor_column(&self) -> usize {
         match self.mode {
-            InputMode::Search => new_cursor_position.clamp(0, self.search_input.len()),
-            InputMode::AddKey => new_cursor_position.clamp(0, self.key_input.len()),
-            InputMode::AddValue => new_cursor_position.clamp(0, self.value_input.len()),
-            InputMode::Edit => new_cursor_position.clamp(0, self.value_input.len()),
+            InputMode::Locked => self.password_input.cursor_graphemes(),
+            InputMode::Search => self.search_input.cursor_width(),
+            InputMode::AddKey | InputMode::Rename | InputMode::FieldName | InputMode::EditTags => {
+                self.key_input.cursor_width()
+            }
+            InputMode::AddValue | InputMode::Edit | InputMode::FieldValue => {
+                self.value_input.cursor_width()
+            }
+            InputMode::NewVault => self.vault_input.cursor_width(),
             _ => 0,
         }
     }
 
-    fn enter_char(&mut self, c: char) {
+    /// insert pasted text into the input of the current mode.
+    /// values keep their line breaks; single-line inputs drop them.
+    fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let single_line = text.replace('\n', "");
+        let mut lines = 1;
         match self.mode {
-            InputMode::Search => {
-                self.search_input.insert(self.cursor_position, c);
-                self.move_cursor_right();
+            InputMode::AddValue | InputMode::Edit | InputMode::FieldValue => {
+                self.value_input.insert_str(&text);
+                lines = text.lines().count();
             }
-            InputMode::AddKey => {
-                self.key_input.insert(self.cursor_position, c);
-                self.move_cursor_right();
+            InputMode::Locked => {
+                self.password_input.insert_str(&single_line);
+                // do not give away the length of a pasted password
+                return;
             }
-            InputMode::AddValue | InputMode::Edit => {
-                self.value_input.insert(self.cursor_position, c);
-                self.move_cursor_right();
+            InputMode::Search => {
+                self.search_input.insert_str(&single_line);
+                self.refilter();
             }
-            _ => {}
+            InputMode::AddKey | InputMode::Rename | InputMode::FieldName | InputMode::EditTags => {
+                self.key_input.insert_str(&single_line)
+            }
+            InputMode::NewVault => self.vault_input.insert_str(&single_line),
+            _ => return,
         }
+        let chars = single_line.chars().count();
+        self.info_message = Some(match lines {
+            0 | 1 => format!("pasted {} characters", chars),
+            lines => format!("pasted {} characters in {} lines", chars, lines),
+        });
     }
 
-    fn delete_char(&mut self) {
-        match self.mode {
-            InputMode::Search => {
-                if self.cursor_position > 0 {
-                    self.search_input.remove(self.cursor_position - 1);
-                    self.move_cursor_left();
-                }
+    /// replace the value input with a new password, or passphrase, using the configured options
+    fn generate(&mut self, passphrase: bool) {
+        let (kind, generated) = match passphrase {
+            true => ("passphrase", generator::passphrase(&self.config.passphrase)),
+            false => ("password", generator::password(&self.config.password)),
+        };
+        match generated {
+            Ok(generated) => {
+                self.value_input.set(&generated.value);
+                self.value_scroll = (0, 0);
+                self.info_message =
+                    Some(format!("generated a {} ({})", kind, generated.describe()));
             }
-            InputMode::AddKey => {
-                if self.cursor_position > 0 {
-                    self.key_input.remove(self.cursor_position - 1);
-                    self.move_cursor_left();
-                }
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    /// apply an editing key to the input of the current mode
+    fn edit_input(&mut self, key: KeyEvent) {
+        let Some(action) = EditAction::from_key(key) else {
+            return;
+        };
+        let ring = Some(&mut self.kill_ring);
+        // the master password never goes into the kill ring
+        let (input, ring) = match self.mode {
+            InputMode::Locked => (&mut self.password_input, None),
+            InputMode::Search => (&mut self.search_input, ring),
+            InputMode::AddKey | InputMode::Rename | InputMode::FieldName | InputMode::EditTags => {
+                (&mut self.key_input, ring)
             }
-            InputMode::AddValue | InputMode::Edit => {
-                if self.cursor_position > 0 {
-                    self.value_input.remove(self.cursor_position - 1);
-                    self.move_cursor_left();
-                }
+            InputMode::AddValue | InputMode::Edit | InputMode::FieldValue => {
+                (&mut self.value_input, ring)
             }
-            _ => {}
+            InputMode::NewVault => (&mut self.vault_input, ring),
+            _ => return,
+        };
+        if action == EditAction::Newline
+            && !matches!(
+                self.mode,
+                InputMode::AddValue | InputMode::Edit | InputMode::FieldValue
+            )
+        {
+            return;
+        }
+        input.apply(action, ring);
+        if let InputMode::Search = self.mode {
+            self.refilter();
         }
     }
 }
@@ -217,151 +775,727 @@ pub fn run_app<B: Backend>(
     kvs: &mut Kvs,
 ) -> io::Result<()> {
     loop {
+        app.lock_if_idle(kvs);
+        app.clipboard.tick();
+
         terminal.draw(|frame| ui(frame, app, kvs))?;
 
-        if let Event::Key(key) = event::read()? {
-            match app.mode {
-                InputMode::Home => match key.code {
-                    KeyCode::Char('q') => {
+        // poll instead of blocking on read so that inactivity can be detected
+        if !event::poll(TICK_RATE)? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) => {
+                app.last_activity = Instant::now();
+                app.error_message = None;
+                app.info_message = None;
+                match handle_key(app, kvs, key) {
+                    Ok(true) => {
+                        app.clipboard.clear_now();
                         return Ok(());
                     }
-                    KeyCode::Char('a') => {
-                        app.mode = InputMode::AddKey;
-                        app.cursor_position = app.key_input.len();
-                    }
-                    KeyCode::Char('s') => {
-                        app.mode = InputMode::Search;
-                        app.cursor_position = app.search_input.len();
-                    }
-                    _ => {}
-                },
-                InputMode::Search => match key.code {
-                    KeyCode::Esc => {
-                        app.mode = InputMode::Home;
-                    }
-                    KeyCode::Backspace => {
-                        app.delete_char();
-                    }
-                    KeyCode::Enter => {
-                        if app.stateful_key_list.items.len() > 0 {
-                            app.mode = InputMode::Select;
-                            app.stateful_key_list.state.select(Some(0));
-                            app.search_input.clear();
-                            app.cursor_position = 0;
-                        }
-                    }
-                    KeyCode::Char(to_insert) => {
-                        app.enter_char(to_insert);
-                    }
-                    _ => {}
-                },
-                InputMode::Select => match key.code {
-                    KeyCode::Esc => {
-                        app.mode = InputMode::Search;
-                        app.search_input.clear();
-                        app.cursor_position = 0;
-                    }
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        app.stateful_key_list.next();
-                    }
-                    KeyCode::Char('k') | KeyCode::Up => {
-                        app.stateful_key_list.previous();
-                    }
-                    KeyCode::Char('d') => {
-                        app.mode = InputMode::Delete;
-                    }
-                    KeyCode::Char('e') => {
-                        if let Some(key) = app.get_selected_key() {
-                            let value = kvs.get(key.as_str()).unwrap();
-                            app.cursor_position = value.len();
-                            app.value_input = value.to_string();
-                            app.mode = InputMode::Edit;
-                        }
-                    }
-                    KeyCode::Enter => {
-                        // copy to clipboard
-                        let selected_key = app.get_selected_key();
-                        let value = kvs.get(&selected_key.unwrap());
-                        let mut clipboard = Clipboard::new().unwrap();
-                        clipboard.set_text(value.unwrap()).unwrap();
-                        std::thread::sleep(std::time::Duration::from_secs(3));
-                    }
-                    _ => {}
-                },
-                InputMode::Edit => match key.code {
-                    KeyCode::Esc => {
-                        app.mode = InputMode::Select;
-                        app.search_input.clear();
-                        app.cursor_position = 0;
-                    }
-                    KeyCode::Backspace => {
-                        app.delete_char();
-                    }
-                    KeyCode::Enter => {
-                        app.key_input = app.get_selected_key().unwrap(); // required to add to kvs
-                        app.add_to_kvs(kvs);
+                    Ok(false) => {}
+                    Err(e) => app.error_message = Some(e.to_string()),
+                }
+            }
+            Event::Paste(text) => {
+                app.last_activity = Instant::now();
+                app.error_message = None;
+                app.paste(&text);
+            }
+            _ => {}
+        }
+    }
+}
 
-                        app.mode = InputMode::Select;
-                        app.search_input.clear();
-                        app.cursor_position = 0;
-                    }
-                    KeyCode::Left => {
-                        app.move_cursor_left();
-                    }
-                    KeyCode::Right => {
-                        app.move_cursor_right();
-                    }
-                    KeyCode::Char(to_insert) => {
-                        app.enter_char(to_insert);
-                    }
-                    _ => {}
-                },
-                InputMode::Delete => match key.code {
-                    KeyCode::Char('y') => {
-                        app.remove_from_kvs(kvs);
-                        app.mode = InputMode::Search;
-                        app.search_input.clear();
-                        app.cursor_position = 0;
-                    }
-                    KeyCode::Esc => {
-                        app.mode = InputMode::Select;
-                    }
-                    _ => {}
-                },
-                InputMode::AddKey => match key.code {
-                    KeyCode::Esc => {
-                        app.mode = InputMode::Home;
-                    }
-                    KeyCode::Backspace => {
-                        app.delete_char();
-                    }
-                    KeyCode::Enter => {
-                        if app.key_input.len() > 0 {
-                            app.mode = InputMode::AddValue;
-                            app.cursor_position = app.value_input.len();
-                        }
-                    }
-                    KeyCode::Char(to_insert) => {
-                        app.enter_char(to_insert);
-                    }
-                    _ => {}
-                },
-                InputMode::AddValue => match key.code {
-                    KeyCode::Esc => {
-                        app.mode = InputMode::AddKey;
-                    }
-                    KeyCode::Backspace => {
-                        app.delete_char();
+/// returns Ok(true) when the app should quit
+fn handle_key(app: &mut App, kvs: &mut Kvs, key: KeyEvent) -> Result<bool, KvsError> {
+    match app.mode {
+        InputMode::Locked => match key.code {
+            KeyCode::Esc => {
+                return Ok(true);
+            }
+            KeyCode::Enter if !app.password_input.is_empty() => {
+                app.unlock(kvs)?;
+            }
+            KeyCode::Tab => {
+                app.show_vaults()?;
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Home => match key.code {
+            KeyCode::Char('q') => {
+                return Ok(true);
+            }
+            KeyCode::Char('a') => {
+                app.language = None;
+                app.overwrite = false;
+                app.value_sensitive = true;
+                app.hide_values();
+                app.mode = InputMode::AddKey;
+            }
+            KeyCode::Char('s') => {
+                app.mode = InputMode::Search;
+            }
+            KeyCode::Char('v') => {
+                app.show_vaults()?;
+            }
+            KeyCode::Char('u') => {
+                app.show_audit(kvs)?;
+            }
+            _ => {}
+        },
+        InputMode::Search => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Home;
+                app.search_input.clear();
+                app.refilter();
+            }
+            KeyCode::Down => {
+                app.select_next();
+            }
+            KeyCode::Up => {
+                app.select_previous();
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.select_next();
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.select_previous();
+            }
+            KeyCode::Enter => {
+                app.copy_selected(kvs)?;
+            }
+            KeyCode::Tab if app.get_selected_key().is_some() => {
+                app.mode = InputMode::Select;
+            }
+            KeyCode::Char('t')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && !app.tag_list.items.is_empty() =>
+            {
+                app.mode = InputMode::Tags;
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Tags => match key.code {
+            KeyCode::Esc | KeyCode::Tab => {
+                app.mode = InputMode::Search;
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.mode = InputMode::Search;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.tag_list.next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.tag_list.previous();
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some((tag, _)) = app
+                    .tag_list
+                    .state
+                    .selected()
+                    .and_then(|i| app.tag_list.items.get(i))
+                    .cloned()
+                {
+                    app.toggle_tag_filter(&tag);
+                }
+                if key.code == KeyCode::Enter {
+                    app.mode = InputMode::Search;
+                }
+            }
+            _ => {}
+        },
+        InputMode::EditTags => match key.code {
+            KeyCode::Esc => {
+                app.key_input.clear();
+                app.mode = InputMode::Select;
+            }
+            KeyCode::Enter => {
+                app.save_tags(kvs)?;
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Select => match key.code {
+            KeyCode::Esc | KeyCode::Tab => {
+                app.mode = InputMode::Search;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.select_next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.select_previous();
+            }
+            KeyCode::Char('v') => {
+                app.toggle_reveal();
+            }
+            KeyCode::Char('f') => {
+                app.show_fields(kvs, None)?;
+            }
+            KeyCode::Char('#') => {
+                app.edit_tags(kvs)?;
+            }
+            KeyCode::Char('t') => {
+                if let Some(key) = app.get_selected_key() {
+                    app.toggle_totp(kvs, &key)?;
+                }
+            }
+            KeyCode::Char('m') => {
+                if let Some(key) = app.get_selected_key() {
+                    kvs.update_entry(&key, |entry| entry.sensitive = !entry.sensitive)?;
+                }
+            }
+            KeyCode::Char('J') => {
+                app.viewer_scroll += 1;
+            }
+            KeyCode::Char('K') => {
+                app.viewer_scroll = app.viewer_scroll.saturating_sub(1);
+            }
+            KeyCode::PageDown => {
+                app.viewer_scroll += VIEWER_PAGE;
+            }
+            KeyCode::PageUp => {
+                app.viewer_scroll = app.viewer_scroll.saturating_sub(VIEWER_PAGE);
+            }
+            KeyCode::Char('d') => {
+                app.mode = InputMode::Delete;
+            }
+            KeyCode::Char('e') => {
+                app.edit_selected(kvs)?;
+            }
+            KeyCode::Char('r') => {
+                if let Some(key) = app.get_selected_key() {
+                    app.key_input.set(&key);
+                    app.mode = InputMode::Rename;
+                }
+            }
+            KeyCode::Char('h') => {
+                if let Some(key) = app.get_selected_key() {
+                    app.history_list = StatefulList::with_items(kvs.get_history(&key)?);
+                    app.history_list.state.select(Some(0));
+                    app.mode = InputMode::History;
+                }
+            }
+            KeyCode::Enter => {
+                app.copy_selected(kvs)?;
+            }
+            _ => {}
+        },
+        InputMode::History => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Select;
+            }
+            KeyCode::Char('v') => {
+                app.toggle_reveal();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.history_list.next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.history_list.previous();
+            }
+            KeyCode::Enter if !app.history_list.items.is_empty() => {
+                if let (Some(key), Some(i)) =
+                    (app.get_selected_key(), app.history_list.state.selected())
+                {
+                    kvs.restore(&key, i)?;
+                    app.mode = InputMode::Select;
+                }
+            }
+            _ => {}
+        },
+        InputMode::Edit => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Select;
+                app.value_input.clear();
+                app.language = None;
+            }
+            KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
+                if let Some(key) = app.get_selected_key() {
+                    app.key_input.set(&key); // required to add to kvs
+                    app.add_to_kvs(kvs)?;
+                }
+                // a value that was refused stays in the editor
+                if app.error_message.is_none() {
+                    app.mode = InputMode::Select;
+                }
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.language = next_language(app.language.as_deref());
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.toggle_reveal();
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.generate(false);
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::ALT) => {
+                app.generate(true);
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Delete => match key.code {
+            KeyCode::Char('y') => {
+                app.remove_from_kvs(kvs)?;
+                app.mode = InputMode::Search;
+            }
+            KeyCode::Char('v') => {
+                app.toggle_reveal();
+            }
+            KeyCode::Esc => {
+                app.mode = InputMode::Select;
+            }
+            _ => {}
+        },
+        InputMode::Rename => match key.code {
+            KeyCode::Esc => {
+                app.key_input.clear();
+                app.mode = InputMode::Select;
+            }
+            KeyCode::Enter if !app.key_input.is_empty() => match app.rename_selected(kvs, false) {
+                Err(KvsError::KeyExists(_)) => app.mode = InputMode::RenameConfirm,
+                result => result?,
+            },
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::RenameConfirm => match key.code {
+            KeyCode::Char('y') => {
+                app.rename_selected(kvs, true)?;
+            }
+            KeyCode::Esc => {
+                app.mode = InputMode::Rename;
+            }
+            _ => {}
+        },
+        InputMode::AddKey => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Home;
+            }
+            KeyCode::Enter if !app.key_input.is_empty() => {
+                app.overwrite = false;
+                if kvs.contains(app.key_input.as_str())? {
+                    app.mode = InputMode::AddExists;
+                } else {
+                    app.mode = InputMode::AddValue;
+                    app.value_scroll = (0, 0);
+                }
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::AddExists => match key.code {
+            KeyCode::Char('y') => {
+                app.overwrite = true;
+                app.mode = InputMode::AddValue;
+                app.value_scroll = (0, 0);
+            }
+            KeyCode::Char('e') => {
+                let key = app.key_input.take();
+                app.sync_key_list(kvs)?;
+                app.select_key(&key);
+                if app.get_selected_key() == Some(key) {
+                    app.edit_selected(kvs)?;
+                }
+            }
+            KeyCode::Esc => {
+                app.mode = InputMode::AddKey;
+            }
+            _ => {}
+        },
+        InputMode::AddValue => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::AddKey;
+            }
+            KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
+                app.add_to_kvs(kvs)?;
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.language = next_language(app.language.as_deref());
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.toggle_reveal();
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.generate(false);
+            }
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::ALT) => {
+                app.generate(true);
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Audit => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Home;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.audit_list.next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.audit_list.previous();
+            }
+            KeyCode::Enter => {
+                let selected = app.audit_list.state.selected();
+                if let Some(finding) = selected.and_then(|i| app.audit_list.items.get(i)) {
+                    let key = finding.key.clone();
+                    app.select_key(&key);
+                    if app.get_selected_key() == Some(key) {
+                        app.edit_selected(kvs)?;
                     }
-                    KeyCode::Enter => {
-                        app.add_to_kvs(kvs);
+                }
+            }
+            _ => {}
+        },
+        InputMode::Fields => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Select;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.field_list.next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.field_list.previous();
+            }
+            KeyCode::Enter => {
+                if let Some(field) = app.selected_field() {
+                    app.copy_field(kvs, &field)?;
+                }
+            }
+            KeyCode::Char('a') => {
+                app.key_input.clear();
+                app.mode = InputMode::FieldName;
+            }
+            KeyCode::Char('e') => {
+                if let Some(field) = app.selected_field() {
+                    app.edit_field(kvs, field)?;
+                }
+            }
+            KeyCode::Char('v') => {
+                app.toggle_reveal();
+            }
+            _ => {}
+        },
+        InputMode::FieldName => match key.code {
+            KeyCode::Esc => {
+                app.key_input.clear();
+                app.mode = InputMode::Fields;
+            }
+            KeyCode::Enter if !app.key_input.as_str().trim().is_empty() => {
+                let field = app.key_input.take().trim().to_string();
+                app.edit_field(kvs, field)?;
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::FieldValue => match key.code {
+            KeyCode::Esc => {
+                app.value_input.clear();
+                app.editing_field = None;
+                app.mode = InputMode::Fields;
+            }
+            KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
+                app.save_field(kvs)?;
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.toggle_reveal();
+            }
+            _ => {
+                app.edit_input(key);
+            }
+        },
+        InputMode::Vault => match key.code {
+            KeyCode::Esc => {
+                app.leave_vaults(kvs);
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.vault_list.next();
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.vault_list.previous();
+            }
+            KeyCode::Char('n') => {
+                app.mode = InputMode::NewVault;
+            }
+            KeyCode::Enter => {
+                if let Some(i) = app.vault_list.state.selected() {
+                    match Vault::named(&app.vault_list.items[i]) {
+                        Ok(vault) => app.switch_vault(kvs, vault, false)?,
+                        Err(e) => app.error_message = Some(e),
                     }
-                    KeyCode::Char(to_insert) => {
-                        app.enter_char(to_insert);
+                }
+            }
+            _ => {}
+        },
+        InputMode::NewVault => match key.code {
+            KeyCode::Esc => {
+                app.mode = InputMode::Vault;
+            }
+            KeyCode::Enter if !app.vault_input.is_empty() => {
+                match Vault::named(app.vault_input.as_str()) {
+                    Ok(vault) => {
+                        app.switch_vault(kvs, vault, true)?;
+                        app.vault_input.clear();
                     }
-                    _ => {}
-                },
+                    Err(e) => app.error_message = Some(e),
+                }
+            }
+            _ => {
+                app.edit_input(key);
             }
+        },
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::ClipboardKind;
+    use crate::kvs::tests::test_kvs;
+
+    fn press(app: &mut App, kvs: &mut Kvs, code: KeyCode) {
+        handle_key(app, kvs, KeyEvent::new(code, KeyModifiers::NONE)).unwrap();
+    }
+
+    fn type_text(app: &mut App, kvs: &mut Kvs, text: &str) {
+        for c in text.chars() {
+            press(app, kvs, KeyCode::Char(c));
         }
     }
+
+    /// an app in search mode over an unlocked vault holding `keys`
+    fn search_app(kvs: &Kvs, keys: &[&str]) -> App {
+        search_app_with(Config::default(), kvs, keys)
+    }
+
+    fn search_app_with(config: Config, kvs: &Kvs, keys: &[&str]) -> App {
+        for key in keys {
+            kvs.insert(key, "value").unwrap();
+        }
+        let mut app = App::new(config, Vault::at(PathBuf::from("test")));
+        app.sync_key_list(kvs).unwrap();
+        app.mode = InputMode::Search;
+        app
+    }
+
+    #[test]
+    fn test_refilter_keeps_selection() {
+        let (mut kvs, _dir) = test_kvs("app_refilter");
+        let mut app = search_app(&kvs, &["aws-prod", "github-token", "gitlab-ci"]);
+
+        type_text(&mut app, &mut kvs, "g");
+        press(&mut app, &mut kvs, KeyCode::Down);
+        let selected = app.get_selected_key().unwrap();
+        assert!(selected.starts_with("git"));
+        // the selected key still matches, so it stays selected
+        type_text(&mut app, &mut kvs, "i");
+        assert_eq!(app.get_selected_key().unwrap(), selected);
+        assert_eq!(app.stateful_key_list.items.len(), 2);
+
+        // once it no longer matches, the best match is selected
+        app.search_input.set("gith");
+        app.refilter();
+        assert_eq!(app.get_selected_key().as_deref(), Some("github-token"));
+        app.search_input.set("zzz");
+        app.refilter();
+        assert_eq!(app.get_selected_key(), None);
+    }
+
+    #[test]
+    fn test_filter_survives_edit_and_delete() {
+        let (mut kvs, _dir) = test_kvs("app_filter");
+        let mut app = search_app(&kvs, &["aws-prod", "github-token", "gitlab-ci"]);
+        type_text(&mut app, &mut kvs, "git");
+        app.select_key("gitlab-ci");
+
+        press(&mut app, &mut kvs, KeyCode::Tab);
+        press(&mut app, &mut kvs, KeyCode::Char('e'));
+        type_text(&mut app, &mut kvs, "2");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert_eq!(kvs.get("gitlab-ci").unwrap(), "value2");
+        assert_eq!(app.search_input.as_str(), "git");
+        assert_eq!(app.get_selected_key().as_deref(), Some("gitlab-ci"));
+
+        press(&mut app, &mut kvs, KeyCode::Char('d'));
+        press(&mut app, &mut kvs, KeyCode::Char('y'));
+        assert_eq!(app.search_input.as_str(), "git");
+        let keys = app
+            .stateful_key_list
+            .items
+            .iter()
+            .map(|m| m.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["github-token"]);
+        assert_eq!(app.get_selected_key().as_deref(), Some("github-token"));
+    }
+
+    #[test]
+    fn test_reveal_follows_selection() {
+        let (mut kvs, _dir) = test_kvs("app_reveal");
+        // a reveal that never times out
+        let config = Config {
+            reveal_secs: 0,
+            ..Config::default()
+        };
+        let mut app = search_app_with(config, &kvs, &["aws-prod", "github-token", "gitlab-ci"]);
+        let reveal = |app: &mut App, kvs: &mut Kvs| {
+            app.mode = InputMode::Select;
+            press(app, kvs, KeyCode::Char('v'));
+            assert!(app.is_revealed());
+        };
+
+        // copying keeps the same key selected, and so revealed
+        reveal(&mut app, &mut kvs);
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert!(app.is_revealed());
+
+        // moving in search mode
+        press(&mut app, &mut kvs, KeyCode::Esc);
+        press(&mut app, &mut kvs, KeyCode::Down);
+        assert!(!app.is_revealed());
+
+        // a filter that selects another key
+        reveal(&mut app, &mut kvs);
+        press(&mut app, &mut kvs, KeyCode::Esc);
+        type_text(&mut app, &mut kvs, "aws");
+        assert!(!app.is_revealed());
+
+        // deleting the selected key
+        reveal(&mut app, &mut kvs);
+        press(&mut app, &mut kvs, KeyCode::Char('d'));
+        press(&mut app, &mut kvs, KeyCode::Char('y'));
+        assert!(!app.is_revealed());
+
+        // editing, and adding a new key
+        app.search_input.clear();
+        app.refilter();
+        reveal(&mut app, &mut kvs);
+        press(&mut app, &mut kvs, KeyCode::Char('e'));
+        assert!(!app.is_revealed());
+        press(&mut app, &mut kvs, KeyCode::Esc);
+        reveal(&mut app, &mut kvs);
+        app.mode = InputMode::Home;
+        press(&mut app, &mut kvs, KeyCode::Char('a'));
+        assert!(!app.is_revealed());
+    }
+
+    #[test]
+    fn test_add_refuses_broken_totp_uri() {
+        let (mut kvs, _dir) = test_kvs("app_totp_uri");
+        let mut app = search_app(&kvs, &[]);
+        app.mode = InputMode::Home;
+        press(&mut app, &mut kvs, KeyCode::Char('a'));
+        type_text(&mut app, &mut kvs, "otp");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+
+        // no secret
+        type_text(&mut app, &mut kvs, "otpauth://totp/x?issuer=x");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert!(app.error_message.is_some());
+        assert!(matches!(app.mode, InputMode::AddValue));
+        assert!(!kvs.contains("otp").unwrap());
+
+        app.error_message = None;
+        app.value_input
+            .set("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQ");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert!(app.error_message.is_none());
+        assert_eq!(kvs.get_entry("otp").unwrap().kind, Kind::Totp);
+    }
+
+    #[test]
+    fn test_add_existing_key() {
+        let (mut kvs, _dir) = test_kvs("app_add_existing");
+        let mut app = search_app(&kvs, &["aws-prod", "github-token"]);
+        let add_key = |app: &mut App, kvs: &mut Kvs, key: &str| {
+            app.mode = InputMode::Home;
+            press(app, kvs, KeyCode::Char('a'));
+            type_text(app, kvs, key);
+            press(app, kvs, KeyCode::Enter);
+        };
+
+        // 'y' replaces the value, keeping the old one in the history
+        add_key(&mut app, &mut kvs, "github-token");
+        assert!(matches!(app.mode, InputMode::AddExists));
+        press(&mut app, &mut kvs, KeyCode::Char('y'));
+        assert!(matches!(app.mode, InputMode::AddValue));
+        type_text(&mut app, &mut kvs, "new");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert!(matches!(app.mode, InputMode::Home));
+        assert_eq!(kvs.get("github-token").unwrap(), "new");
+        assert_eq!(kvs.get_history("github-token").unwrap()[0].value, "value");
+
+        // 'e' edits the existing entry instead
+        add_key(&mut app, &mut kvs, "github-token");
+        press(&mut app, &mut kvs, KeyCode::Char('e'));
+        assert!(matches!(app.mode, InputMode::Edit));
+        assert_eq!(app.get_selected_key().as_deref(), Some("github-token"));
+        assert_eq!(app.value_input.as_str(), "new");
+        press(&mut app, &mut kvs, KeyCode::Esc);
+
+        // a key added meanwhile is only replaced after asking again
+        add_key(&mut app, &mut kvs, "gitlab-ci");
+        assert!(matches!(app.mode, InputMode::AddValue));
+        kvs.insert("gitlab-ci", "other").unwrap();
+        type_text(&mut app, &mut kvs, "mine");
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert!(matches!(app.mode, InputMode::AddExists));
+        assert_eq!(kvs.get("gitlab-ci").unwrap(), "other");
+        press(&mut app, &mut kvs, KeyCode::Char('y'));
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        assert_eq!(kvs.get("gitlab-ci").unwrap(), "mine");
+    }
+
+    #[test]
+    fn test_lock_after_inactivity() {
+        let (mut kvs, _dir) = test_kvs("app_lock");
+        let config = Config {
+            lock_timeout_secs: 60,
+            reveal_secs: 0,
+            clipboard: ClipboardKind::Notice,
+            ..Config::default()
+        };
+        let mut app = search_app_with(config, &kvs, &["aws-prod", "github-token"]);
+        type_text(&mut app, &mut kvs, "git");
+        press(&mut app, &mut kvs, KeyCode::Tab);
+        press(&mut app, &mut kvs, KeyCode::Enter);
+        press(&mut app, &mut kvs, KeyCode::Char('v'));
+        assert_eq!(app.clipboard.notice(), Some("value"));
+        app.mode = InputMode::Search;
+        let kill = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        handle_key(&mut app, &mut kvs, kill).unwrap();
+
+        app.lock_if_idle(&mut kvs);
+        assert!(kvs.is_unlocked());
+        app.last_activity = Instant::now() - Duration::from_secs(61);
+        app.lock_if_idle(&mut kvs);
+
+        assert!(matches!(app.mode, InputMode::Locked));
+        assert!(app.lock_message.is_some());
+        assert!(!kvs.is_unlocked());
+        assert!(!app.is_revealed());
+        assert_eq!(app.clipboard.notice(), None);
+        assert!(app.search_input.is_empty());
+        assert!(app.key_list.is_empty());
+        assert!(app.stateful_key_list.items.is_empty());
+        // nothing killed before locking can be yanked after it
+        app.search_input
+            .apply(EditAction::Yank, Some(&mut app.kill_ring));
+        assert!(app.search_input.is_empty());
+    }
 }
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::entry::{now, Entry, Kind};
+use crate::generator::strength;
+
+/// values shorter than this are flagged whatever their entropy
+const MIN_LENGTH: usize = 12;
+/// values below this estimate are flagged as weak
+const MIN_ENTROPY: f64 = 60.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// the same value is stored under these other keys
+    Reused(Vec<String>),
+    Weak {
+        length: usize,
+        entropy: f64,
+    },
+    /// not updated for this many days
+    Stale(u64),
+}
+
+/// something wrong with the value of a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub key: String,
+    pub issue: Issue,
+}
+
+impl Finding {
+    pub fn describe(&self) -> String {
+        match &self.issue {
+            Issue::Reused(keys) => format!("reused: same value as {}", keys.join(", ")),
+            Issue::Weak { length, entropy } => format!(
+                "weak: {} character{}, ~{:.0} bits of entropy ({})",
+                length,
+                if *length == 1 { "" } else { "s" },
+                entropy,
+                strength(*entropy)
+            ),
+            Issue::Stale(days) => format!("stale: not updated for {} days", days),
+        }
+    }
+}
+
+/// rough entropy of a value from its length and the character classes it uses;
+/// it cannot see dictionary words, so it errs on the generous side
+pub fn estimate_entropy(value: &str) -> f64 {
+    let uses = |class: fn(&char) -> bool| value.chars().any(|c| class(&c));
+    let pool = [
+        (uses(char::is_ascii_lowercase), 26),
+        (uses(char::is_ascii_uppercase), 26),
+        (uses(char::is_ascii_digit), 10),
+        (value.chars().any(|c| !c.is_ascii_alphanumeric()), 33),
+    ]
+    .iter()
+    .filter(|(used, _)| *used)
+    .map(|(_, size)| size)
+    .sum::<usize>();
+    value.chars().count() as f64 * (pool.max(1) as f64).log2()
+}
+
+/// check every sensitive entry; entries with masking turned off, such as snippets, are skipped.
+/// `max_age_days` of 0 disables the stale check.
+pub fn audit(entries: &[(String, Entry)], max_age_days: u64) -> Vec<Finding> {
+    let entries = entries
+        .iter()
+        .filter(|(_, entry)| entry.sensitive)
+        .collect::<Vec<_>>();
+    let mut keys_by_value: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (key, entry) in &entries {
+        keys_by_value
+            .entry(entry.value.as_str())
+            .or_default()
+            .push(key.as_str());
+    }
+
+    let mut findings = vec![];
+    for (key, entry) in entries {
+        let others = keys_by_value[entry.value.as_str()]
+            .iter()
+            .filter(|other| **other != key)
+            .map(|other| other.to_string())
+            .collect::<Vec<String>>();
+        if !others.is_empty() {
+            findings.push(Finding {
+                key: key.clone(),
+                issue: Issue::Reused(others),
+            });
+        }
+
+        // multi-line values are notes or keys, and TOTP secrets are generated, not passwords
+        let length = entry.value.chars().count();
+        let entropy = estimate_entropy(&entry.value);
+        let is_password = entry.kind == Kind::Secret && !entry.value.contains('\n');
+        if is_password && (length < MIN_LENGTH || entropy < MIN_ENTROPY) {
+            findings.push(Finding {
+                key: key.clone(),
+                issue: Issue::Weak { length, entropy },
+            });
+        }
+
+        let days = now().saturating_sub(entry.updated) / 86400;
+        if max_age_days > 0 && days >= max_age_days {
+            findings.push(Finding {
+                key: key.clone(),
+                issue: Issue::Stale(days),
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_entropy() {
+        assert!((estimate_entropy("123456") - 6.0 * 10f64.log2()).abs() < 1e-9);
+        assert!(estimate_entropy("GGKRB4LvuBWEM8m+pQ+i") > 120.0);
+        assert_eq!(estimate_entropy(""), 0.0);
+    }
+
+    #[test]
+    fn test_audit() {
+        let mut old = Entry::new("GGKRB4LvuBWEM8m+pQ+i");
+        old.updated = now() - 400 * 86400;
+        let mut snippet = Entry::new("hunter2");
+        snippet.sensitive = false;
+        let entries = vec![
+            ("a".to_string(), Entry::new("hunter2")),
+            ("b".to_string(), Entry::new("hunter2")),
+            ("c".to_string(), old),
+            ("d".to_string(), snippet),
+        ];
+        let findings = audit(&entries, 365);
+        let describe = |key: &str| {
+            findings
+                .iter()
+                .filter(|f| f.key == key)
+                .map(Finding::describe)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(describe("a")[0], "reused: same value as b");
+        assert!(describe("a")[1].starts_with("weak: 7 characters"));
+        assert_eq!(describe("c"), vec!["stale: not updated for 400 days"]);
+        assert!(describe("d").is_empty());
+        assert!(audit(&entries, 0).iter().all(|f| f.key != "c"));
+    }
+}
@@ -0,0 +1,390 @@
+use std::error::Error;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::audit::audit;
+use crate::config::{passmr_dir, Config};
+use crate::entry::{parse_tags, Kind};
+use crate::fuzzy::Query;
+use crate::generator;
+use crate::highlight;
+use crate::kvs::Kvs;
+use crate::totp::Totp;
+use crate::vault::{Vault, DEFAULT_VAULT};
+
+/// A lightweight CLI tool for managing key-value.
+/// Launches the TUI when no subcommand is given.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Named vault to open, kept under $HOME/.passmr
+    #[arg(long, global = true, env = "PASSMR_VAULT")]
+    pub vault: Option<String>,
+    /// Open the vault at this path instead of a named vault
+    #[arg(long, global = true, value_name = "PATH")]
+    pub db: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// vaults are created by the first launch of the TUI or the first `set` (the default vault
+    /// only), or by `set --create-vault`; every other command needs an existing vault
+    pub fn creates_vault(&self) -> bool {
+        let default_vault =
+            self.db.is_none() && self.vault.as_deref().unwrap_or(DEFAULT_VAULT) == DEFAULT_VAULT;
+        match &self.command {
+            None => default_vault,
+            Some(Command::Set { create_vault, .. }) => *create_vault || default_vault,
+            Some(_) => false,
+        }
+    }
+
+    /// the vault given by `--db`, `--vault` or `PASSMR_VAULT`, in that order, or the default one
+    pub fn vault(&self) -> Result<Vault, String> {
+        match (&self.db, &self.vault) {
+            (Some(path), _) => Ok(Vault::at(path.clone())),
+            (None, Some(name)) => Vault::named(name),
+            (None, None) => Vault::named(DEFAULT_VAULT),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the value of a key
+    Get {
+        key: String,
+        /// Print this field instead of the value, e.g. username
+        #[arg(long, value_name = "NAME")]
+        field: Option<String>,
+    },
+    /// Store a value, read from stdin (or prompted for on a terminal)
+    Set {
+        key: String,
+        /// Store a field of an existing key instead of the value, e.g. username
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["lang", "totp"])]
+        field: Option<String>,
+        /// Language to highlight the value as, e.g. sh, sql or yaml;
+        /// `auto` to detect it, `plain` for none
+        #[arg(long, value_name = "LANGUAGE")]
+        lang: Option<String>,
+        /// The value is a TOTP secret (base32 or an otpauth:// URI)
+        #[arg(long)]
+        totp: bool,
+        /// Create the vault, and set its master password, if it does not exist yet
+        #[arg(long)]
+        create_vault: bool,
+    },
+    /// Print the current one-time code of a TOTP entry
+    Otp { key: String },
+    /// Remove a key-value
+    Rm { key: String },
+    /// List keys, optionally only those containing a pattern;
+    /// `tag:NAME` words list only keys with that tag
+    Ls { pattern: Option<String> },
+    /// Print the tags of a key, or replace them with the given ones
+    Tag {
+        key: String,
+        /// e.g. `aws prod` or `aws,prod`
+        tags: Vec<String>,
+        /// Remove every tag
+        #[arg(long, conflicts_with = "tags")]
+        clear: bool,
+    },
+    /// Edit the value of an existing key in $EDITOR
+    Edit { key: String },
+    /// Print a random password, or a passphrase with --words
+    Generate(GenerateArgs),
+    /// List reused, weak and stale values
+    Audit {
+        /// Flag values not updated for this many days (0 disables the check);
+        /// defaults to `audit_max_age_days` in config.toml
+        #[arg(long, value_name = "DAYS")]
+        max_age: Option<u64>,
+    },
+}
+
+/// options not given fall back to `[password]`/`[passphrase]` in config.toml
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Number of characters
+    #[arg(short, long)]
+    length: Option<usize>,
+    /// Generate a passphrase of this many words instead
+    #[arg(short, long)]
+    words: Option<usize>,
+    /// Word separator of a passphrase
+    #[arg(long)]
+    separator: Option<String>,
+    /// Capitalize the words of a passphrase
+    #[arg(long)]
+    capitalize: bool,
+    #[arg(long)]
+    no_lowercase: bool,
+    #[arg(long)]
+    no_uppercase: bool,
+    #[arg(long)]
+    no_digits: bool,
+    #[arg(long)]
+    no_symbols: bool,
+    /// Allow characters that are easily confused, like l, 1, O and 0
+    #[arg(long)]
+    ambiguous: bool,
+}
+
+/// print the generated value on stdout and its strength on stderr, so that it can be piped
+pub fn generate(args: GenerateArgs) -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    let generated = match args.words {
+        Some(words) => {
+            let mut options = config.passphrase;
+            options.words = words;
+            if let Some(separator) = args.separator {
+                options.separator = separator;
+            }
+            options.capitalize |= args.capitalize;
+            generator::passphrase(&options)?
+        }
+        None => {
+            let mut options = config.password;
+            if let Some(length) = args.length {
+                options.length = length;
+            }
+            options.lowercase &= !args.no_lowercase;
+            options.uppercase &= !args.no_uppercase;
+            options.digits &= !args.no_digits;
+            options.symbols &= !args.no_symbols;
+            options.exclude_ambiguous &= !args.ambiguous;
+            generator::password(&options)?
+        }
+    };
+    println!("{}", generated.value);
+    eprintln!("{}", generated.describe());
+    Ok(())
+}
+
+/// unlock with `PASSMR_PASSWORD` if set, otherwise prompt on the terminal.
+/// a vault without a master password is only set up when `initialize` is set.
+fn unlock(kvs: &mut Kvs, initialize: bool) -> Result<(), Box<dyn Error>> {
+    if !kvs.is_initialized()? && !initialize {
+        return Err("the vault has no master password yet: open it in the TUI, \
+                    or store a value with `set --create-vault`"
+            .into());
+    }
+    if let Ok(password) = std::env::var("PASSMR_PASSWORD") {
+        kvs.unlock(&password)?;
+    } else if kvs.is_initialized()? {
+        let password = rpassword::prompt_password("Master password: ")?;
+        kvs.unlock(&password)?;
+    } else {
+        let password = rpassword::prompt_password("New master password: ")?;
+        let confirm = rpassword::prompt_password("Confirm master password: ")?;
+        if password != confirm {
+            return Err("passwords do not match".into());
+        }
+        kvs.unlock(&password)?;
+    }
+    Ok(())
+}
+
+/// read a value from stdin, dropping the trailing newline added by `echo` and friends
+fn read_value() -> Result<String, Box<dyn Error>> {
+    let value = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password("Value: ")?
+    } else {
+        let mut value = String::new();
+        std::io::stdin().read_to_string(&mut value)?;
+        strip_newline(value)
+    };
+    if value.is_empty() {
+        return Err("value must not be empty".into());
+    }
+    Ok(value)
+}
+
+fn strip_newline(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
+
+/// a private directory holding the decrypted value while it is edited; it is removed,
+/// together with any swap or backup files the editor left in it, when dropped
+struct EditDir(PathBuf);
+
+impl EditDir {
+    /// on tmpfs when available, so that the value never reaches a persistent disk
+    fn create() -> Result<EditDir, Box<dyn Error>> {
+        let tmpfs = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain([PathBuf::from("/dev/shm")])
+            .find(|dir| dir.is_dir());
+        let base = match tmpfs {
+            Some(dir) => dir,
+            None => passmr_dir()?,
+        };
+        let path = base.join(format!("passmr-edit-{}", std::process::id()));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&path)?;
+        Ok(EditDir(path))
+    }
+}
+
+impl Drop for EditDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// let the user edit `value` in their editor, using a private temp file
+fn edit_in_editor(value: &str) -> Result<String, Box<dyn Error>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let dir = EditDir::create()?;
+    let path = dir.0.join("value");
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(&path)?, value.as_bytes())?;
+
+    // $EDITOR may carry arguments, e.g. `code --wait`
+    let mut editor_args = editor.split_whitespace();
+    let status = std::process::Command::new(editor_args.next().unwrap_or("vi"))
+        .args(editor_args)
+        .arg(&path)
+        .status()
+        .map_err(|e| format!("failed to run {}: {}", editor, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status).into());
+    }
+    Ok(strip_newline(std::fs::read_to_string(&path)?))
+}
+
+/// run `command`; `initialize` sets up the master password of a vault that has none yet
+pub fn run(command: Command, kvs: &mut Kvs, initialize: bool) -> Result<(), Box<dyn Error>> {
+    unlock(kvs, initialize)?;
+    match command {
+        Command::Get { key, field: None } => {
+            println!("{}", kvs.get(&key)?);
+            kvs.touch(&key)?;
+        }
+        Command::Get {
+            key,
+            field: Some(field),
+        } => {
+            let entry = kvs.get_entry(&key)?;
+            let value = entry
+                .field(&field)
+                .ok_or(format!("{} has no field {}", key, field))?;
+            println!("{}", value);
+            kvs.touch(&key)?;
+        }
+        Command::Set {
+            key,
+            field: Some(field),
+            ..
+        } => {
+            let value = read_value()?;
+            kvs.set_field(&key, &field, &value)?;
+        }
+        Command::Set {
+            key,
+            field: None,
+            lang,
+            totp,
+            ..
+        } => {
+            let lang = match lang.as_deref() {
+                Some("auto") => Some(None),
+                Some(lang) if highlight::is_known(lang) => Some(Some(lang)),
+                Some(lang) => return Err(format!("unknown language: {}", lang).into()),
+                None => None,
+            };
+            let value = read_value()?;
+            let is_totp = totp || value.starts_with("otpauth://totp/");
+            // check before storing, so that a typo does not leave a broken entry
+            if is_totp {
+                Totp::parse(&value)?;
+            }
+            kvs.insert_with(&key, &value, |entry| {
+                if is_totp {
+                    entry.kind = Kind::Totp;
+                }
+                // without --lang an existing entry keeps its language
+                if let Some(lang) = lang {
+                    entry.language = lang.map(str::to_string);
+                }
+            })?;
+        }
+        Command::Otp { key } => {
+            let entry = kvs.get_entry(&key)?;
+            if entry.kind != Kind::Totp {
+                return Err(format!("{} is not a TOTP secret", key).into());
+            }
+            let (code, remaining) = Totp::parse(&entry.value)?.current();
+            println!("{}", code);
+            eprintln!("valid for {}s", remaining);
+            kvs.touch(&key)?;
+        }
+        Command::Rm { key } => {
+            kvs.delete(&key)?;
+        }
+        Command::Ls { pattern } => {
+            let query = Query::parse(&pattern.unwrap_or_default());
+            let pattern = query.text.to_lowercase();
+            for key in kvs.get_key_vec()? {
+                if !key.to_lowercase().contains(&pattern) {
+                    continue;
+                }
+                // only read the entry when filtering by tag
+                if query.tags.is_empty() || query.has_tags(&kvs.get_entry(&key)?.tags) {
+                    println!("{}", key);
+                }
+            }
+        }
+        Command::Tag { key, tags, clear } => {
+            if tags.is_empty() && !clear {
+                let tags = kvs.get_entry(&key)?.tags;
+                if !tags.is_empty() {
+                    println!("{}", tags.join(" "));
+                }
+            } else {
+                let tags = parse_tags(&tags.join(" "));
+                kvs.update_entry(&key, |entry| entry.set_tags(tags))?;
+            }
+        }
+        Command::Edit { key } => {
+            let value = kvs.get(&key)?;
+            let edited = edit_in_editor(&value)?;
+            if edited.is_empty() {
+                return Err("value must not be empty".into());
+            }
+            if edited != value {
+                kvs.insert(&key, &edited)?;
+            }
+        }
+        Command::Generate(args) => generate(args)?,
+        Command::Audit { max_age } => {
+            let max_age = max_age.unwrap_or(Config::load()?.audit_max_age_days);
+            for finding in audit(&kvs.get_entries()?, max_age) {
+                println!("{}: {}", finding.key, finding.describe());
+            }
+        }
+    }
+    // the process exits right after, so do not rely on sled's background flush
+    kvs.flush()?;
+    Ok(())
+}
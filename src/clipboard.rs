@@ -0,0 +1,398 @@
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use data_encoding::BASE64;
+
+use crate::config::ClipboardKind;
+
+/// somewhere a copied value can be put
+pub trait ClipboardProvider {
+    fn name(&self) -> &'static str;
+    fn set_text(&mut self, text: &str) -> Result<(), String>;
+    /// current content, or None if the provider cannot read it back
+    fn get_text(&mut self) -> Option<String>;
+    fn clear(&mut self) -> Result<(), String> {
+        self.set_text("")
+    }
+    /// text the UI has to show because it could not be put anywhere else
+    fn notice(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// the system clipboard through arboard (X11, Wayland, macOS)
+struct ArboardProvider(arboard::Clipboard);
+
+impl ArboardProvider {
+    fn new() -> Result<Self, String> {
+        arboard::Clipboard::new()
+            .map(ArboardProvider)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl ClipboardProvider for ArboardProvider {
+    fn name(&self) -> &'static str {
+        "arboard"
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.0.set_text(text).map_err(|e| e.to_string())
+    }
+
+    fn get_text(&mut self) -> Option<String> {
+        self.0.get_text().ok()
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.0.clear().map_err(|e| e.to_string())
+    }
+}
+
+/// an external helper such as wl-copy or xclip
+struct CommandProvider {
+    name: &'static str,
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+}
+
+const COMMAND_PROVIDERS: &[CommandProvider] = &[
+    CommandProvider {
+        name: "wl-copy",
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+    },
+    CommandProvider {
+        name: "xclip",
+        copy: &["xclip", "-selection", "clipboard"],
+        paste: &["xclip", "-selection", "clipboard", "-o"],
+    },
+    CommandProvider {
+        name: "xsel",
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+    },
+    CommandProvider {
+        name: "pbcopy",
+        copy: &["pbcopy"],
+        paste: &["pbpaste"],
+    },
+];
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+impl CommandProvider {
+    /// the first helper that is installed and has a display to talk to
+    fn detect() -> Option<&'static CommandProvider> {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let x11 = std::env::var_os("DISPLAY").is_some();
+        COMMAND_PROVIDERS.iter().find(|provider| {
+            let usable = match provider.name {
+                "wl-copy" => wayland,
+                "xclip" | "xsel" => x11,
+                _ => cfg!(target_os = "macos"),
+            };
+            usable && on_path(provider.copy[0])
+        })
+    }
+}
+
+impl ClipboardProvider for &'static CommandProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        let mut child = Command::new(self.copy[0])
+            .args(&self.copy[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("{}: {}", self.name, e))?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("{}: {}", self.name, e))?;
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{} exited with {}", self.name, status)),
+            Err(e) => Err(format!("{}: {}", self.name, e)),
+        }
+    }
+
+    fn get_text(&mut self) -> Option<String> {
+        let output = Command::new(self.paste[0])
+            .args(&self.paste[1..])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8(output.stdout).ok()
+    }
+}
+
+/// the OSC 52 escape sequence, which asks the terminal to set its clipboard.
+/// works over SSH, but the content cannot be read back.
+struct Osc52Provider;
+
+impl Osc52Provider {
+    fn write(payload: &str) -> Result<(), String> {
+        let sequence = format!("\x1b]52;c;{}\x07", payload);
+        // tmux only forwards the sequence when wrapped in its passthrough escape
+        let sequence = match std::env::var_os("TMUX") {
+            Some(_) => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+            None => sequence,
+        };
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn is_supported() -> bool {
+        let term = std::env::var("TERM").unwrap_or_default();
+        std::io::stdout().is_terminal() && !matches!(term.as_str(), "" | "dumb" | "linux")
+    }
+}
+
+impl ClipboardProvider for Osc52Provider {
+    fn name(&self) -> &'static str {
+        "OSC 52"
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        Osc52Provider::write(&BASE64.encode(text.as_bytes()))
+    }
+
+    fn get_text(&mut self) -> Option<String> {
+        None
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        // anything that is not valid base64 clears the selection
+        Osc52Provider::write("!")
+    }
+}
+
+/// last resort: nothing to copy to, so the UI shows the value until it is cleared
+#[derive(Default)]
+struct NoticeProvider {
+    shown: Option<String>,
+}
+
+impl ClipboardProvider for NoticeProvider {
+    fn name(&self) -> &'static str {
+        "notice"
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.shown = Some(text.to_string());
+        Ok(())
+    }
+
+    fn get_text(&mut self) -> Option<String> {
+        self.shown.clone()
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.shown = None;
+        Ok(())
+    }
+
+    fn notice(&self) -> Option<&str> {
+        self.shown.as_deref()
+    }
+}
+
+/// the provider chosen in config; `Auto` walks the chain until one is usable
+pub fn detect(kind: ClipboardKind) -> Box<dyn ClipboardProvider> {
+    match kind {
+        ClipboardKind::Auto => {
+            if let Ok(provider) = ArboardProvider::new() {
+                Box::new(provider)
+            } else if let Some(provider) = CommandProvider::detect() {
+                Box::new(provider)
+            } else if Osc52Provider::is_supported() {
+                Box::new(Osc52Provider)
+            } else {
+                Box::<NoticeProvider>::default()
+            }
+        }
+        ClipboardKind::Arboard => match ArboardProvider::new() {
+            Ok(provider) => Box::new(provider),
+            Err(_) => Box::<NoticeProvider>::default(),
+        },
+        ClipboardKind::Command => match CommandProvider::detect() {
+            Some(provider) => Box::new(provider),
+            None => Box::<NoticeProvider>::default(),
+        },
+        ClipboardKind::Osc52 => Box::new(Osc52Provider),
+        ClipboardKind::Notice => Box::<NoticeProvider>::default(),
+    }
+}
+
+/// a copied secret waiting to be cleared
+struct PendingClear {
+    secret: String,
+    deadline: Instant,
+}
+
+/// copies secrets to the clipboard and clears them again after a timeout
+pub struct SecretClipboard {
+    kind: ClipboardKind,
+    /// detected on first copy and kept: on X11 the content is served by its owner
+    provider: Option<Box<dyn ClipboardProvider>>,
+    pending: Option<PendingClear>,
+    timeout: Option<Duration>,
+}
+
+impl SecretClipboard {
+    /// `timeout` of None keeps copied secrets in the clipboard
+    pub fn new(kind: ClipboardKind, timeout: Option<Duration>) -> Self {
+        Self {
+            kind,
+            provider: None,
+            pending: None,
+            timeout,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_provider(provider: Box<dyn ClipboardProvider>, timeout: Option<Duration>) -> Self {
+        Self {
+            kind: ClipboardKind::Auto,
+            provider: Some(provider),
+            pending: None,
+            timeout,
+        }
+    }
+
+    fn provider(&mut self) -> &mut Box<dyn ClipboardProvider> {
+        let kind = self.kind;
+        self.provider.get_or_insert_with(|| detect(kind))
+    }
+
+    /// copy without blocking; the clear happens later from `tick`
+    pub fn copy(&mut self, secret: String) -> Result<(), String> {
+        self.provider().set_text(secret.as_str())?;
+        // a notice has to disappear even if clearing is disabled
+        let timeout = match self.provider().notice() {
+            Some(_) => self.timeout.or(Some(Duration::from_secs(30))),
+            None => self.timeout,
+        };
+        self.pending = timeout.map(|timeout| PendingClear {
+            secret,
+            deadline: Instant::now() + timeout,
+        });
+        Ok(())
+    }
+
+    /// name of the provider used for the last copy
+    pub fn provider_name(&self) -> Option<&'static str> {
+        self.provider.as_ref().map(|provider| provider.name())
+    }
+
+    /// value to show on screen when there is no clipboard to copy to
+    pub fn notice(&self) -> Option<&str> {
+        self.provider
+            .as_ref()
+            .and_then(|provider| provider.notice())
+    }
+
+    /// time left until the copied secret is cleared
+    pub fn remaining(&self) -> Option<Duration> {
+        self.pending
+            .as_ref()
+            .map(|pending| pending.deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// called from the event loop; clears the secret once its deadline has passed
+    pub fn tick(&mut self) {
+        if self.remaining() == Some(Duration::ZERO) {
+            self.clear_now();
+        }
+    }
+
+    /// clear the pending secret, unless something else was copied over it in the meantime.
+    /// providers that cannot read the clipboard back are cleared unconditionally.
+    pub fn clear_now(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            let provider = self.provider();
+            let still_ours = match provider.get_text() {
+                Some(text) => text == pending.secret,
+                None => true,
+            };
+            if still_ours {
+                let _ = provider.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// an in-memory clipboard that the test can inspect and overwrite
+    struct FakeProvider(Rc<RefCell<String>>);
+
+    impl ClipboardProvider for FakeProvider {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn set_text(&mut self, text: &str) -> Result<(), String> {
+            *self.0.borrow_mut() = text.to_string();
+            Ok(())
+        }
+
+        fn get_text(&mut self) -> Option<String> {
+            Some(self.0.borrow().clone())
+        }
+    }
+
+    #[test]
+    fn test_clear_after_timeout() {
+        let content = Rc::new(RefCell::new(String::new()));
+        let provider = Box::new(FakeProvider(content.clone()));
+        let mut clipboard = SecretClipboard::with_provider(provider, Some(Duration::ZERO));
+
+        clipboard.copy("secret".to_string()).unwrap();
+        assert_eq!(*content.borrow(), "secret");
+        clipboard.tick();
+        assert_eq!(*content.borrow(), "");
+        assert_eq!(clipboard.remaining(), None);
+    }
+
+    #[test]
+    fn test_keep_text_copied_by_someone_else() {
+        let content = Rc::new(RefCell::new(String::new()));
+        let provider = Box::new(FakeProvider(content.clone()));
+        let mut clipboard = SecretClipboard::with_provider(provider, Some(Duration::ZERO));
+
+        clipboard.copy("secret".to_string()).unwrap();
+        *content.borrow_mut() = "something else".to_string();
+        clipboard.tick();
+        assert_eq!(*content.borrow(), "something else");
+    }
+
+    #[test]
+    fn test_notice_is_hidden_on_clear() {
+        let mut clipboard = SecretClipboard::with_provider(Box::<NoticeProvider>::default(), None);
+
+        clipboard.copy("secret".to_string()).unwrap();
+        assert_eq!(clipboard.notice(), Some("secret"));
+        clipboard.clear_now();
+        assert_eq!(clipboard.notice(), None);
+    }
+}
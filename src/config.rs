@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::generator::{PassphraseOptions, PasswordOptions};
+
+/// `$HOME/.passmr`, created on first use
+pub fn passmr_dir() -> std::io::Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "home directory not found",
+    ))?;
+    let passmr_dir = home_dir.join(".passmr");
+    std::fs::create_dir_all(&passmr_dir)?;
+    Ok(passmr_dir)
+}
+
+/// where copied values go; see `clipboard::detect`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardKind {
+    /// the first of arboard, an external helper, OSC 52 and an on-screen notice that works
+    #[default]
+    Auto,
+    Arboard,
+    /// wl-copy, xclip, xsel or pbcopy
+    Command,
+    Osc52,
+    /// show the value on screen instead of copying it
+    Notice,
+}
+
+/// user settings read from `$HOME/.passmr/config.toml`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// lock the vault after this many idle seconds (0 disables auto-lock)
+    pub lock_timeout_secs: u64,
+    /// clear a copied value from the clipboard after this many seconds (0 keeps it)
+    pub clipboard_clear_secs: u64,
+    /// mask a revealed value again after this many seconds (0 keeps it shown)
+    pub reveal_secs: u64,
+    pub clipboard: ClipboardKind,
+    /// flag values not updated for this many days in the audit (0 disables the check)
+    pub audit_max_age_days: u64,
+    /// defaults of the password generator
+    pub password: PasswordOptions,
+    /// defaults of the passphrase generator
+    pub passphrase: PassphraseOptions,
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let path = passmr_dir().map_err(|e| e.to_string())?.join("config.toml");
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        secs_to_duration(self.lock_timeout_secs)
+    }
+
+    pub fn reveal_timeout(&self) -> Option<Duration> {
+        secs_to_duration(self.reveal_secs)
+    }
+
+    pub fn clipboard_timeout(&self) -> Option<Duration> {
+        secs_to_duration(self.clipboard_clear_secs)
+    }
+}
+
+/// a timeout from the config, where 0 turns it off
+fn secs_to_duration(secs: u64) -> Option<Duration> {
+    match secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            lock_timeout_secs: 300,
+            clipboard_clear_secs: 30,
+            reveal_secs: 10,
+            audit_max_age_days: 365,
+            clipboard: ClipboardKind::Auto,
+            password: PasswordOptions::default(),
+            passphrase: PassphraseOptions::default(),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// the only key derivation function so far, recorded in the vault header
+pub const KDF_ARGON2ID: &[u8] = b"argon2id-v19";
+
+/// argon2id costs a vault key was derived with, stored in the vault header so that new vaults
+/// can use other costs without locking existing vaults out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    /// memory in KiB
+    pub m_cost: u32,
+    /// iterations
+    pub t_cost: u32,
+    /// lanes
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    /// the costs new vaults are created with: the argon2 defaults
+    pub const RECOMMENDED: KdfParams = KdfParams {
+        m_cost: Params::DEFAULT_M_COST,
+        t_cost: Params::DEFAULT_T_COST,
+        p_cost: Params::DEFAULT_P_COST,
+    };
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        [self.m_cost, self.t_cost, self.p_cost]
+            .iter()
+            .flat_map(|cost| cost.to_be_bytes())
+            .collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<KdfParams> {
+        if bytes.len() != 12 {
+            return None;
+        }
+        let costs = bytes
+            .chunks_exact(4)
+            .map(|cost| u32::from_be_bytes([cost[0], cost[1], cost[2], cost[3]]))
+            .collect::<Vec<u32>>();
+        Some(KdfParams {
+            m_cost: costs[0],
+            t_cost: costs[1],
+            p_cost: costs[2],
+        })
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::RECOMMENDED
+    }
+}
+
+/// seals and opens values with a key derived from the master password
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// derive the vault key from the master password with argon2id
+    pub fn derive(password: &str, salt: &[u8], params: &KdfParams) -> Result<Cipher, String> {
+        let params = Params::new(params.m_cost, params.t_cost, params.p_cost, None)
+            .map_err(|e| e.to_string())?;
+        let mut key = Key::default();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        Ok(Cipher {
+            aead: XChaCha20Poly1305::new(&key),
+        })
+    }
+
+    pub fn generate_salt() -> [u8; SALT_LEN] {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    /// encrypt plaintext bound to `aad`, returning `nonce || ciphertext`;
+    /// it only opens again with the same `aad`
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, payload)
+            .expect("encryption with a valid key cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    /// decrypt `nonce || ciphertext`, returning None if it was not sealed by this key with `aad`
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad,
+        };
+        self.aead.decrypt(XNonce::from_slice(nonce), payload).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open() {
+        let salt = Cipher::generate_salt();
+        let cipher = Cipher::derive("password", &salt, &KdfParams::default()).unwrap();
+        let sealed = cipher.seal(b"value", b"key");
+        assert_eq!(cipher.open(&sealed, b"key"), Some(b"value".to_vec()));
+        assert_eq!(cipher.open(&sealed, b"other"), None);
+
+        let wrong = Cipher::derive("wrong", &salt, &KdfParams::default()).unwrap();
+        assert_eq!(wrong.open(&sealed, b"key"), None);
+    }
+
+    #[test]
+    fn test_kdf_params() {
+        let params = KdfParams {
+            m_cost: 8 * 1024,
+            t_cost: 1,
+            p_cost: 1,
+        };
+        assert_eq!(KdfParams::from_bytes(&params.to_bytes()), Some(params));
+        assert_eq!(KdfParams::from_bytes(b"short"), None);
+
+        // the costs are part of the key
+        let salt = Cipher::generate_salt();
+        let sealed = Cipher::derive("password", &salt, &params)
+            .unwrap()
+            .seal(b"value", b"");
+        let recommended = Cipher::derive("password", &salt, &KdfParams::RECOMMENDED).unwrap();
+        assert_eq!(recommended.open(&sealed, b""), None);
+    }
+}
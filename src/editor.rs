@@ -0,0 +1,466 @@
+use std::ops::Range;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// number of killed texts kept for yanking
+const KILL_RING_SIZE: usize = 10;
+
+/// an editing command, bound to readline-style keys by `from_key`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditAction {
+    Insert(char),
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    /// to the same column on the line above or below
+    Up,
+    Down,
+    /// to the start/end of the line
+    Home,
+    End,
+    Newline,
+    DeleteBefore,
+    DeleteAfter,
+    KillToStart,
+    KillToEnd,
+    KillWordBefore,
+    KillWordAfter,
+    /// insert the most recently killed text
+    Yank,
+    /// right after a yank, replace the yanked text with the previous kill
+    YankPop,
+}
+
+impl EditAction {
+    pub fn from_key(key: KeyEvent) -> Option<EditAction> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let action = match key.code {
+            KeyCode::Left if ctrl || alt => EditAction::WordLeft,
+            KeyCode::Left => EditAction::Left,
+            KeyCode::Right if ctrl || alt => EditAction::WordRight,
+            KeyCode::Right => EditAction::Right,
+            KeyCode::Up => EditAction::Up,
+            KeyCode::Down => EditAction::Down,
+            KeyCode::Enter if alt => EditAction::Newline,
+            KeyCode::Home => EditAction::Home,
+            KeyCode::End => EditAction::End,
+            KeyCode::Backspace if alt => EditAction::KillWordBefore,
+            KeyCode::Backspace => EditAction::DeleteBefore,
+            KeyCode::Delete => EditAction::DeleteAfter,
+            KeyCode::Char(c) if ctrl => match c {
+                'a' => EditAction::Home,
+                'e' => EditAction::End,
+                'b' => EditAction::Left,
+                'f' => EditAction::Right,
+                'h' => EditAction::DeleteBefore,
+                'j' => EditAction::Newline,
+                'p' => EditAction::Up,
+                'n' => EditAction::Down,
+                'd' => EditAction::DeleteAfter,
+                'u' => EditAction::KillToStart,
+                'k' => EditAction::KillToEnd,
+                'w' => EditAction::KillWordBefore,
+                'y' => EditAction::Yank,
+                _ => return None,
+            },
+            KeyCode::Char(c) if alt => match c {
+                'b' => EditAction::WordLeft,
+                'f' => EditAction::WordRight,
+                'd' => EditAction::KillWordAfter,
+                'y' => EditAction::YankPop,
+                _ => return None,
+            },
+            KeyCode::Char(c) => EditAction::Insert(c),
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+/// recently killed texts, shared by all inputs so that text can be moved between them
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: Vec<String>,
+    /// entry inserted by the last yank or yank-pop
+    index: usize,
+}
+
+impl KillRing {
+    fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push(text);
+        if self.entries.len() > KILL_RING_SIZE {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len() - 1;
+    }
+
+    fn latest(&mut self) -> Option<&str> {
+        self.index = self.entries.len().checked_sub(1)?;
+        Some(&self.entries[self.index])
+    }
+
+    fn previous(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.index = (self.index + self.entries.len() - 1) % self.entries.len();
+        Some(&self.entries[self.index])
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = 0;
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().any(char::is_alphanumeric)
+}
+
+/// a text input with a cursor that moves by grapheme cluster, so that non-ASCII
+/// input (e.g. `é`, `日本`, emoji) is never split. it is a single line unless
+/// line breaks are inserted with `EditAction::Newline` or pasted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineEditor {
+    text: String,
+    /// byte offset into `text`, always on a grapheme boundary
+    cursor: usize,
+    /// text inserted by the last action if it was a yank, for yank-pop
+    yanked: Option<Range<usize>>,
+    /// column kept while moving up and down through shorter lines
+    goal_column: Option<usize>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// replace the text and put the cursor at its end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.yanked = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.yanked = None;
+    }
+
+    /// the text, leaving the editor empty
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        self.yanked = None;
+        std::mem::take(&mut self.text)
+    }
+
+    /// `ring` of None discards killed text instead of keeping it for yanking
+    pub fn apply(&mut self, action: EditAction, ring: Option<&mut KillRing>) {
+        let yanked = self.yanked.take();
+        let goal_column = self.goal_column.take();
+        match action {
+            EditAction::Insert(c) => self.insert_char(c),
+            EditAction::Left => self.move_left(),
+            EditAction::Right => self.move_right(),
+            EditAction::WordLeft => self.cursor = self.word_start(self.cursor),
+            EditAction::WordRight => self.cursor = self.word_end(self.cursor),
+            EditAction::Up => self.move_vertically(false, goal_column),
+            EditAction::Down => self.move_vertically(true, goal_column),
+            EditAction::Home => self.cursor = self.line_start(self.cursor),
+            EditAction::End => self.cursor = self.line_end(self.cursor),
+            EditAction::Newline => self.insert_char('\n'),
+            EditAction::DeleteBefore => self.delete_before(),
+            EditAction::DeleteAfter => {
+                let end = self.next_boundary(self.cursor);
+                self.text.replace_range(self.cursor..end, "");
+            }
+            EditAction::KillToStart => self.kill(self.line_start(self.cursor)..self.cursor, ring),
+            EditAction::KillToEnd => {
+                // at the end of a line, join the next one
+                let end = match self.line_end(self.cursor) {
+                    end if end == self.cursor && end < self.text.len() => end + 1,
+                    end => end,
+                };
+                self.kill(self.cursor..end, ring)
+            }
+            EditAction::KillWordBefore => {
+                self.kill(self.word_start(self.cursor)..self.cursor, ring)
+            }
+            EditAction::KillWordAfter => self.kill(self.cursor..self.word_end(self.cursor), ring),
+            EditAction::Yank => {
+                if let Some(text) = ring.and_then(|ring| ring.latest()) {
+                    let start = self.cursor;
+                    self.text.insert_str(start, text);
+                    self.cursor = start + text.len();
+                    self.yanked = Some(start..self.cursor);
+                }
+            }
+            EditAction::YankPop => {
+                if let (Some(range), Some(ring)) = (yanked, ring) {
+                    if let Some(text) = ring.previous() {
+                        self.text.replace_range(range.clone(), text);
+                        self.cursor = range.start + text.len();
+                        self.yanked = Some(range.start..self.cursor);
+                    }
+                }
+            }
+        }
+    }
+
+    fn kill(&mut self, range: Range<usize>, ring: Option<&mut KillRing>) {
+        let killed = self.text.drain(range.clone()).collect::<String>();
+        self.cursor = range.start;
+        if let Some(ring) = ring {
+            ring.push(killed);
+        }
+    }
+
+    /// insert text at the cursor, e.g. a paste
+    pub fn insert_str(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.text.insert_str(self.cursor, text);
+        self.cursor = self.next_boundary(self.cursor + text.len() - 1);
+        self.yanked = None;
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.text.split('\n').count()
+    }
+
+    fn insert_char(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        // a combining character joins the grapheme before it; keep the cursor after the cluster
+        self.cursor = self.next_boundary(self.cursor.saturating_sub(1));
+    }
+
+    /// remove the grapheme before the cursor
+    fn delete_before(&mut self) {
+        if self.cursor > 0 {
+            let start = self.previous_boundary(self.cursor);
+            self.text.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        }
+    }
+
+    fn move_left(&mut self) {
+        self.cursor = self.previous_boundary(self.cursor);
+    }
+
+    fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+    }
+
+    /// number of graphemes, i.e. what a user would call characters
+    pub fn grapheme_count(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    /// number of graphemes before the cursor
+    pub fn cursor_graphemes(&self) -> usize {
+        self.text[..self.cursor].graphemes(true).count()
+    }
+
+    /// terminal columns taken by the text before the cursor on its line; wide characters take two
+    pub fn cursor_width(&self) -> usize {
+        self.text[self.line_start(self.cursor)..self.cursor].width()
+    }
+
+    /// line of the cursor, counted from 0
+    pub fn cursor_line(&self) -> usize {
+        self.text[..self.cursor].matches('\n').count()
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |i| offset + i)
+    }
+
+    fn move_vertically(&mut self, down: bool, goal_column: Option<usize>) {
+        let column = goal_column.unwrap_or_else(|| self.cursor_width());
+        self.goal_column = Some(column);
+        let line_start = self.line_start(self.cursor);
+        let target_start = if down {
+            match self.text[self.cursor..].find('\n') {
+                Some(i) => self.cursor + i + 1,
+                None => return,
+            }
+        } else if line_start > 0 {
+            self.line_start(line_start - 1)
+        } else {
+            return;
+        };
+        // the grapheme at `column`, or the end of a shorter line
+        let line_end = self.line_end(target_start);
+        let mut width = 0;
+        self.cursor = line_end;
+        for (i, g) in self.text[target_start..line_end].grapheme_indices(true) {
+            width += g.width();
+            if width > column {
+                self.cursor = target_start + i;
+                break;
+            }
+        }
+    }
+
+    fn previous_boundary(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .take_while(|&i| i < offset)
+            .last()
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(i, g)| i + g.len())
+            .find(|&end| end > offset)
+            .unwrap_or(self.text.len())
+    }
+
+    /// start of the word before `offset`, skipping any separators in between
+    fn word_start(&self, offset: usize) -> usize {
+        let mut graphemes = self.text[..offset].grapheme_indices(true).rev().peekable();
+        let mut start = offset;
+        while let Some((i, _)) = graphemes.next_if(|(_, g)| !is_word(g)) {
+            start = i;
+        }
+        while let Some((i, _)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            start = i;
+        }
+        start
+    }
+
+    /// end of the word after `offset`, skipping any separators in between
+    fn word_end(&self, offset: usize) -> usize {
+        let mut graphemes = self.text[offset..].grapheme_indices(true).peekable();
+        let mut end = offset;
+        while let Some((i, g)) = graphemes.next_if(|(_, g)| !is_word(g)) {
+            end = offset + i + g.len();
+        }
+        while let Some((i, g)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            end = offset + i + g.len();
+        }
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_ascii_input() {
+        let mut editor = LineEditor::new();
+        for c in "aé日本".chars() {
+            editor.insert_char(c);
+        }
+        assert_eq!(editor.cursor_width(), 6);
+        editor.move_left();
+        editor.insert_char('x');
+        assert_eq!(editor.as_str(), "aé日x本");
+        editor.delete_before();
+        editor.delete_before();
+        assert_eq!(editor.as_str(), "aé本");
+        assert_eq!(editor.cursor_graphemes(), 2);
+    }
+
+    #[test]
+    fn test_kill_and_yank() {
+        let mut ring = KillRing::default();
+        let mut editor = LineEditor::new();
+        editor.set("user@example.com pass");
+        editor.apply(EditAction::KillWordBefore, Some(&mut ring));
+        assert_eq!(editor.as_str(), "user@example.com ");
+        editor.apply(EditAction::WordLeft, None);
+        editor.apply(EditAction::WordLeft, None);
+        editor.apply(EditAction::KillToEnd, Some(&mut ring));
+        assert_eq!(editor.as_str(), "user@");
+
+        let mut other = LineEditor::new();
+        other.apply(EditAction::Yank, Some(&mut ring));
+        assert_eq!(other.as_str(), "example.com ");
+        other.apply(EditAction::YankPop, Some(&mut ring));
+        assert_eq!(other.as_str(), "pass");
+        other.apply(EditAction::Home, None);
+        other.apply(EditAction::DeleteAfter, None);
+        assert_eq!(other.as_str(), "ass");
+
+        // without a ring, killed text is gone
+        other.apply(EditAction::KillToEnd, None);
+        other.apply(EditAction::Yank, None);
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut editor = LineEditor::new();
+        editor.set("ab");
+        editor.apply(EditAction::Left, None);
+        editor.insert_str("1\n23");
+        assert_eq!(editor.as_str(), "a1\n23b");
+        assert_eq!(editor.line_count(), 2);
+        assert_eq!((editor.cursor_line(), editor.cursor_width()), (1, 2));
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let mut editor = LineEditor::new();
+        editor.set("first line\nab\n日本語です");
+        editor.apply(EditAction::Up, None);
+        assert_eq!((editor.cursor_line(), editor.cursor_width()), (1, 2));
+        editor.apply(EditAction::Up, None);
+        // the column is kept across the shorter line
+        assert_eq!((editor.cursor_line(), editor.cursor_width()), (0, 10));
+        editor.apply(EditAction::Down, None);
+        editor.apply(EditAction::Down, None);
+        assert_eq!((editor.cursor_line(), editor.cursor_width()), (2, 10));
+
+        editor.apply(EditAction::Home, None);
+        editor.apply(EditAction::Newline, None);
+        assert_eq!(editor.as_str(), "first line\nab\n\n日本語です");
+        editor.apply(EditAction::Up, None);
+        editor.apply(EditAction::KillToEnd, None);
+        assert_eq!(editor.as_str(), "first line\nab\n日本語です");
+        assert_eq!(editor.line_count(), 3);
+    }
+
+    #[test]
+    fn test_combining_characters() {
+        let mut editor = LineEditor::new();
+        editor.set("ne\u{301}");
+        assert_eq!(editor.grapheme_count(), 2);
+        editor.move_left();
+        assert_eq!(editor.cursor_graphemes(), 1);
+        editor.move_right();
+        editor.insert_char('\u{301}');
+        editor.delete_before();
+        assert_eq!(editor.as_str(), "n");
+    }
+}
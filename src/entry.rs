@@ -0,0 +1,241 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::totp::Totp;
+
+/// seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// human readable age of a timestamp, e.g. "3 days ago"
+pub fn format_age(timestamp: u64) -> String {
+    let secs = now().saturating_sub(timestamp);
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        86400..=2591999 => (secs / 86400, "day"),
+        2592000..=31535999 => (secs / 2592000, "month"),
+        _ => (secs / 31536000, "year"),
+    };
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+/// name under which the main value of an entry is listed among its fields
+pub const VALUE_FIELD: &str = "value";
+
+/// field names that are shown even when the entry is masked
+const PUBLIC_FIELDS: &[&str] = &["username", "user", "email", "login", "url", "website"];
+
+/// an extra named value of an entry, e.g. a username or url
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+}
+
+impl Field {
+    pub fn is_public(name: &str) -> bool {
+        PUBLIC_FIELDS.contains(&name.to_lowercase().as_str())
+    }
+}
+
+/// how the value of an entry is used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// copied as is
+    #[default]
+    Secret,
+    /// a TOTP secret; its current one-time code is shown and copied instead
+    Totp,
+}
+
+/// what is stored (encrypted) for each key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub value: String,
+    pub created: u64,
+    pub updated: u64,
+    /// when the value was last copied or printed
+    pub last_accessed: Option<u64>,
+    pub access_count: u64,
+    /// syntax used to highlight the value, `None` to detect it from the value
+    #[serde(default)]
+    pub language: Option<String>,
+    /// mask the value on screen until it is revealed
+    #[serde(default = "sensitive_by_default")]
+    pub sensitive: bool,
+    #[serde(default)]
+    pub kind: Kind,
+    /// named values besides the main one, in the order they were added
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    /// lowercase labels for filtering, sorted
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn sensitive_by_default() -> bool {
+    true
+}
+
+impl Entry {
+    pub fn new(value: &str) -> Entry {
+        let now = now();
+        Entry {
+            value: value.to_string(),
+            created: now,
+            updated: now,
+            last_accessed: None,
+            access_count: 0,
+            language: None,
+            sensitive: true,
+            kind: Kind::Secret,
+            fields: vec![],
+            tags: vec![],
+        }
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        if self.value != value {
+            self.value = value.to_string();
+            self.updated = now();
+        }
+    }
+
+    /// `VALUE_FIELD` for the main value, or a named field
+    pub fn field(&self, name: &str) -> Option<&str> {
+        if name == VALUE_FIELD {
+            return Some(&self.value);
+        }
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.value.as_str())
+    }
+
+    /// `VALUE_FIELD` followed by the names of the other fields
+    pub fn field_names(&self) -> Vec<String> {
+        std::iter::once(VALUE_FIELD.to_string())
+            .chain(self.fields.iter().map(|field| field.name.clone()))
+            .collect()
+    }
+
+    /// add or replace a named field; an empty value removes it
+    pub fn set_field(&mut self, name: &str, value: &str) {
+        if name == VALUE_FIELD {
+            self.set_value(value);
+            return;
+        }
+        match self.fields.iter().position(|field| field.name == name) {
+            Some(i) if value.is_empty() => {
+                self.fields.remove(i);
+            }
+            Some(i) if self.fields[i].value != value => self.fields[i].value = value.to_string(),
+            Some(_) => return,
+            None if value.is_empty() => return,
+            None => self.fields.push(Field {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+        }
+        self.updated = now();
+    }
+
+    /// the text to copy for a field; the main value of a TOTP entry gives its current code
+    pub fn copy_text(&self, name: &str) -> Result<String, String> {
+        let value = self
+            .field(name)
+            .ok_or_else(|| format!("no field {:?}", name))?;
+        match self.kind {
+            Kind::Totp if name == VALUE_FIELD => Ok(Totp::parse(value)?.current().0),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    /// tags are labels, not part of the value, so `updated` is left alone
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    pub fn touch(&mut self) {
+        self.last_accessed = Some(now());
+        self.access_count += 1;
+    }
+}
+
+/// tags separated by commas or spaces, e.g. "aws, prod": lowercased, sorted and deduplicated;
+/// a leading '#' is dropped
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// a value that was replaced by an edit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryItem {
+    pub value: String,
+    pub replaced: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(now()), "just now");
+        assert_eq!(format_age(now() - 3600), "1 hour ago");
+        assert_eq!(format_age(now() - 3 * 86400), "3 days ago");
+    }
+
+    #[test]
+    fn test_entry_defaults() {
+        let json = r#"{"value":"v","created":1,"updated":1,"last_accessed":null,"access_count":0}"#;
+        let entry: Entry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.language, None);
+        assert!(entry.sensitive);
+        assert_eq!(entry.kind, Kind::Secret);
+        assert!(entry.fields.is_empty());
+        assert!(entry.tags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("prod, AWS #ci,,aws"), vec!["aws", "ci", "prod"]);
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut entry = Entry::new("s3cret");
+        entry.set_field("username", "alice");
+        entry.set_field("url", "https://example.com");
+        assert_eq!(entry.field_names(), vec!["value", "username", "url"]);
+        assert_eq!(entry.copy_text("username").unwrap(), "alice");
+        assert_eq!(entry.copy_text(VALUE_FIELD).unwrap(), "s3cret");
+        assert!(entry.copy_text("missing").is_err());
+
+        entry.set_field("username", "bob");
+        entry.set_field("url", "");
+        assert_eq!(entry.field_names(), vec!["value", "username"]);
+        assert_eq!(entry.field("username"), Some("bob"));
+        assert!(Field::is_public("Username") && !Field::is_public("pin"));
+    }
+}
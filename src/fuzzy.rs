@@ -0,0 +1,209 @@
+use std::cmp::Reverse;
+
+use crate::entry::now;
+
+/// base score of every matched character
+const SCORE_MATCH: i64 = 16;
+/// a match at the start of a word, e.g. the `t` of `github-token`
+const BONUS_BOUNDARY: i64 = 8;
+/// a match on the first character of the key
+const BONUS_PREFIX: i64 = 8;
+/// a match right after the previous one
+const BONUS_CONSECUTIVE: i64 = 16;
+/// per character skipped between matches
+const PENALTY_GAP: i64 = 1;
+/// cap on the penalty for characters before the first match, so that a word
+/// late in the key is not outranked by a weaker match near the start
+const MAX_PENALTY_LEADING: i64 = 3;
+
+/// a key that contains the characters of the search pattern in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub text: String,
+    pub score: i64,
+    /// char indices of `text` that matched the pattern, for highlighting
+    pub positions: Vec<usize>,
+}
+
+/// the start of a word: after a separator or at a lower-to-upper case change
+fn is_boundary(prev: Option<char>, c: char) -> bool {
+    match prev {
+        None => true,
+        Some(prev) => !prev.is_alphanumeric() || (prev.is_lowercase() && c.is_uppercase()),
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// case-insensitive subsequence match of `pattern` against `text`, picking the
+/// highest scoring alignment rather than the leftmost one
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(fold)
+        .collect();
+    let chars: Vec<char> = text.chars().collect();
+    let (m, n) = (pattern.len(), chars.len());
+    if m == 0 {
+        return Some(Match {
+            text: text.to_string(),
+            score: 0,
+            positions: vec![],
+        });
+    }
+    if m > n {
+        return None;
+    }
+
+    // best[i][j]: best score with pattern[..=i] matched and pattern[i] at chars[j],
+    // from[i][j]: where pattern[i - 1] was matched for that score
+    let mut best: Vec<Vec<Option<i64>>> = vec![vec![None; n]; m];
+    let mut from = vec![vec![0; n]; m];
+    for i in 0..m {
+        for j in i..n {
+            if fold(chars[j]) != pattern[i] {
+                continue;
+            }
+            let prev_char = j.checked_sub(1).map(|k| chars[k]);
+            let mut bonus = SCORE_MATCH;
+            if is_boundary(prev_char, chars[j]) {
+                bonus += BONUS_BOUNDARY;
+            }
+            if i == 0 {
+                if j == 0 {
+                    bonus += BONUS_PREFIX;
+                }
+                best[i][j] = Some(bonus - (PENALTY_GAP * j as i64).min(MAX_PENALTY_LEADING));
+                continue;
+            }
+            let previous = (i - 1..j)
+                .filter_map(|k| {
+                    let score = best[i - 1][k]?;
+                    let score = if k + 1 == j {
+                        score + BONUS_CONSECUTIVE
+                    } else {
+                        score - PENALTY_GAP * (j - k - 1) as i64
+                    };
+                    Some((score, k))
+                })
+                .max_by_key(|&(score, k)| (score, Reverse(k)));
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(score + bonus);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, score) = (0..n)
+        .filter_map(|j| best[m - 1][j].map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, Reverse(j)))?;
+    let mut positions = vec![j; m];
+    for i in (1..m).rev() {
+        j = from[i][j];
+        positions[i - 1] = j;
+    }
+    Some(Match {
+        text: text.to_string(),
+        score,
+        positions,
+    })
+}
+
+/// prefix of a search word that filters by tag instead of matching the key
+pub const TAG_PREFIX: &str = "tag:";
+
+/// a search input split into tag filters and the text matched against keys,
+/// e.g. `tag:aws prod` is the tag `aws` and the text `prod`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub text: String,
+    /// lowercase tags a key must all have
+    pub tags: Vec<String>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Query {
+        let mut words = vec![];
+        let mut tags = vec![];
+        for word in input.split_whitespace() {
+            match word.strip_prefix(TAG_PREFIX) {
+                Some(tag) if !tag.is_empty() => tags.push(tag.to_lowercase()),
+                // a bare `tag:` is still being typed
+                Some(_) => {}
+                None => words.push(word),
+            }
+        }
+        Query {
+            text: words.join(" "),
+            tags,
+        }
+    }
+
+    /// whether a key with `tags` passes the tag filters
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        self.tags.iter().all(|tag| tags.contains(tag))
+    }
+}
+
+/// extra score for keys used recently, fading out over a month
+pub fn recency_bonus(last_used: Option<u64>) -> i64 {
+    match last_used.map(|timestamp| now().saturating_sub(timestamp)) {
+        Some(0..=3599) => 16,
+        Some(3600..=86399) => 12,
+        Some(86400..=604799) => 8,
+        Some(604800..=2591999) => 4,
+        _ => 0,
+    }
+}
+
+/// best match first; ties go to the shorter key, then alphabetical order
+pub fn sort_matches(matches: &mut [Match]) {
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.text.chars().count().cmp(&b.text.chars().count()))
+            .then(a.text.cmp(&b.text))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let m = fuzzy_match("ghtok", "github-token").unwrap();
+        assert_eq!(m.positions, vec![0, 3, 7, 8, 9]);
+        assert!(fuzzy_match("GHTOK", "github-token").is_some());
+        assert!(fuzzy_match("tokgh", "github-token").is_none());
+        assert_eq!(fuzzy_match("", "github-token").unwrap().score, 0);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut matches = ["my-stuff-aws", "aws-prod", "a-w-s", "laws"]
+            .iter()
+            .filter_map(|key| fuzzy_match("aws", key))
+            .collect::<Vec<Match>>();
+        sort_matches(&mut matches);
+        let keys = matches.iter().map(|m| m.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["aws-prod", "my-stuff-aws", "laws", "a-w-s"]);
+
+        let recent = fuzzy_match("a", "abc").unwrap().score + recency_bonus(Some(now()));
+        assert!(recent > fuzzy_match("a", "abc").unwrap().score);
+        assert_eq!(recency_bonus(None), 0);
+    }
+
+    #[test]
+    fn test_query() {
+        let query = Query::parse("tag:AWS  prod tag: tag:ci");
+        assert_eq!(query.text, "prod");
+        assert_eq!(query.tags, vec!["aws", "ci"]);
+        assert!(query.has_tags(&["aws".to_string(), "ci".to_string(), "x".to_string()]));
+        assert!(!query.has_tags(&["aws".to_string()]));
+        assert!(Query::parse("gh").has_tags(&[]));
+    }
+}
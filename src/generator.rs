@@ -0,0 +1,211 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+/// the BIP-39 English wordlist: 2048 short, distinct words
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&*+-./:;=?@^_~";
+/// characters that are easily mistaken for one another when read or retyped
+const AMBIGUOUS: &str = "Il1O0o";
+
+/// `[password]` in config.toml
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_ambiguous: bool,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: true,
+        }
+    }
+}
+
+/// `[passphrase]` in config.toml
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+        }
+    }
+}
+
+/// a generated secret and its estimated strength
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generated {
+    pub value: String,
+    /// bits of entropy, assuming the options used are known to an attacker
+    pub entropy: f64,
+}
+
+impl Generated {
+    /// e.g. "~118 bits of entropy, very strong"
+    pub fn describe(&self) -> String {
+        format!(
+            "~{:.0} bits of entropy, {}",
+            self.entropy,
+            strength(self.entropy)
+        )
+    }
+}
+
+pub fn strength(entropy: f64) -> &'static str {
+    match entropy {
+        e if e < 40.0 => "weak",
+        e if e < 60.0 => "fair",
+        e if e < 80.0 => "strong",
+        _ => "very strong",
+    }
+}
+
+/// a random password with at least one character of each enabled class
+pub fn password(options: &PasswordOptions) -> Result<Generated, String> {
+    let classes = [
+        (options.lowercase, LOWERCASE),
+        (options.uppercase, UPPERCASE),
+        (options.digits, DIGITS),
+        (options.symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, class)| {
+        class
+            .chars()
+            .filter(|c| !(options.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+            .collect::<Vec<char>>()
+    })
+    .collect::<Vec<_>>();
+    if classes.is_empty() {
+        return Err("no character classes enabled".to_string());
+    }
+    if options.length < classes.len() {
+        return Err(format!(
+            "a password needs at least {} characters to use every enabled character class",
+            classes.len()
+        ));
+    }
+
+    let pool = classes.concat();
+    let mut chars = classes
+        .iter()
+        .filter_map(|class| class.choose(&mut OsRng))
+        .copied()
+        .collect::<Vec<char>>();
+    while chars.len() < options.length {
+        chars.extend(pool.choose(&mut OsRng));
+    }
+    chars.shuffle(&mut OsRng);
+    Ok(Generated {
+        value: chars.into_iter().collect(),
+        entropy: options.length as f64 * (pool.len() as f64).log2(),
+    })
+}
+
+/// random words from the embedded wordlist, diceware style
+pub fn passphrase(options: &PassphraseOptions) -> Result<Generated, String> {
+    if options.words == 0 {
+        return Err("a passphrase needs at least one word".to_string());
+    }
+    let words = WORDLIST.lines().collect::<Vec<&str>>();
+    let value = (0..options.words)
+        .filter_map(|_| words.choose(&mut OsRng))
+        .map(|word| match options.capitalize {
+            true => capitalize(word),
+            false => word.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(&options.separator);
+    Ok(Generated {
+        value,
+        entropy: options.words as f64 * (words.len() as f64).log2(),
+    })
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password() {
+        let generated = password(&PasswordOptions::default()).unwrap();
+        assert_eq!(generated.value.chars().count(), 20);
+        assert!(generated.value.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(generated.value.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(generated.value.chars().any(|c| c.is_ascii_digit()));
+        assert!(generated.value.chars().any(|c| SYMBOLS.contains(c)));
+        assert!(!generated.value.chars().any(|c| AMBIGUOUS.contains(c)));
+
+        let digits = PasswordOptions {
+            length: 6,
+            lowercase: false,
+            uppercase: false,
+            symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        let pin = password(&digits).unwrap();
+        assert!(pin.value.chars().all(|c| c.is_ascii_digit()));
+        assert!((pin.entropy - 6.0 * 10f64.log2()).abs() < 1e-9);
+        assert_eq!(strength(pin.entropy), "weak");
+
+        let none = PasswordOptions {
+            digits: false,
+            ..digits
+        };
+        assert!(password(&none).is_err());
+    }
+
+    #[test]
+    fn test_passphrase() {
+        assert_eq!(WORDLIST.lines().count(), 2048);
+        let options = PassphraseOptions {
+            words: 4,
+            separator: " ".to_string(),
+            capitalize: true,
+        };
+        let generated = passphrase(&options).unwrap();
+        let words = generated.value.split(' ').collect::<Vec<_>>();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert_eq!(generated.entropy, 44.0);
+        assert!(passphrase(&PassphraseOptions {
+            words: 0,
+            ..options
+        })
+        .is_err());
+    }
+}
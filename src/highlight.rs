@@ -0,0 +1,212 @@
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// language tag that turns highlighting off for an entry
+pub const PLAIN: &str = "plain";
+/// languages offered when cycling with Ctrl-l, after "auto"
+pub const LANGUAGES: &[&str] = &["sh", "sql", "yaml", "json", "py", PLAIN];
+
+const THEME: &str = "base16-ocean.dark";
+
+/// first words of a statement that mark a value as SQL
+const SQL_KEYWORDS: &[&str] = &[
+    "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "WITH", "GRANT", "REVOKE",
+    "TRUNCATE",
+];
+
+/// commands a shell one-liner commonly starts with
+const SHELL_COMMANDS: &[&str] = &[
+    "sudo",
+    "export",
+    "cd",
+    "ls",
+    "cat",
+    "echo",
+    "grep",
+    "awk",
+    "sed",
+    "find",
+    "curl",
+    "wget",
+    "ssh",
+    "scp",
+    "rsync",
+    "tar",
+    "git",
+    "docker",
+    "kubectl",
+    "helm",
+    "aws",
+    "gcloud",
+    "az",
+    "psql",
+    "mysql",
+    "redis-cli",
+    "systemctl",
+    "openssl",
+    "for",
+    "if",
+    "while",
+];
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME_CELL: OnceLock<Theme> = OnceLock::new();
+    THEME_CELL.get_or_init(|| {
+        ThemeSet::load_defaults()
+            .themes
+            .remove(THEME)
+            .unwrap_or_default()
+    })
+}
+
+/// whether `language` can be used as a language tag
+pub fn is_known(language: &str) -> bool {
+    language == PLAIN || syntaxes().find_syntax_by_token(language).is_some()
+}
+
+/// the language after `current` in the Ctrl-l cycle, `None` being "auto"
+pub fn next_language(current: Option<&str>) -> Option<String> {
+    let next = match current {
+        None => LANGUAGES.first(),
+        Some(current) => match LANGUAGES.iter().position(|l| *l == current) {
+            Some(i) => LANGUAGES.get(i + 1),
+            None => LANGUAGES.first(),
+        },
+    };
+    next.map(|l| l.to_string())
+}
+
+/// guess the language of a value; single words, such as passwords, are never highlighted
+pub fn detect(value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    let first_line = value.lines().next()?;
+    if let Some(interpreter) = first_line.strip_prefix("#!") {
+        return Some(if interpreter.contains("python") {
+            "py"
+        } else {
+            "sh"
+        });
+    }
+    if value.starts_with(['{', '['])
+        && serde_json::from_str::<serde_json::Value>(value).is_ok_and(|v| !v.is_string())
+    {
+        return Some("json");
+    }
+    if !value.contains(char::is_whitespace) {
+        return None;
+    }
+    let first_word = first_line.split_whitespace().next()?;
+    if SQL_KEYWORDS.contains(&first_word.to_uppercase().as_str()) {
+        return Some("sql");
+    }
+    if is_yaml(value) {
+        return Some("yaml");
+    }
+    let first_word = first_word.trim_start_matches('$');
+    if SHELL_COMMANDS.contains(&first_word)
+        || value.contains(" | ")
+        || value.contains(" && ")
+        || value.contains("$(")
+    {
+        return Some("sh");
+    }
+    None
+}
+
+/// a document marker, or several lines that are all `key: value` pairs or list items
+fn is_yaml(value: &str) -> bool {
+    if value.starts_with("---") {
+        return true;
+    }
+    let lines = value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>();
+    let is_pair = |line: &str| match line.split_once(':') {
+        Some((key, rest)) => {
+            !key.is_empty()
+                && !key.contains(char::is_whitespace)
+                && (rest.is_empty() || rest.starts_with(' '))
+        }
+        None => false,
+    };
+    lines.len() > 1
+        && lines.iter().any(|line| is_pair(line))
+        && lines
+            .iter()
+            .all(|line| is_pair(line) || line.starts_with("- "))
+}
+
+/// `value` as coloured lines, or `None` for plain text and unknown languages
+pub fn highlight(value: &str, language: &str) -> Option<Vec<Line<'static>>> {
+    if language == PLAIN {
+        return None;
+    }
+    let syntax = syntaxes().find_syntax_by_token(language)?;
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    let mut lines = vec![];
+    for line in LinesWithEndings::from(value) {
+        let ranges = highlighter.highlight_line(line, syntaxes()).ok()?;
+        let spans = ranges
+            .into_iter()
+            .map(|(style, text)| {
+                let color = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                Span::styled(
+                    text.trim_end_matches(['\r', '\n']).to_string(),
+                    Style::default().fg(color),
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(spans));
+    }
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("select * from users where id = 1"), Some("sql"));
+        assert_eq!(detect("#!/bin/bash\necho hi"), Some("sh"));
+        assert_eq!(detect("kubectl get pods -n prod"), Some("sh"));
+        assert_eq!(detect("ps aux | grep passmr"), Some("sh"));
+        assert_eq!(
+            detect("user: admin\nhost: db.local\nport: 5432"),
+            Some("yaml")
+        );
+        assert_eq!(detect(r#"{"token": "abc"}"#), Some("json"));
+        assert_eq!(detect("hunter2"), None);
+        assert_eq!(detect("{Sup3r:s3cret}"), None);
+        assert_eq!(detect("correct horse battery staple"), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let lines = highlight("select 1;\nselect 2;", "sql").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0]
+            .spans
+            .iter()
+            .all(|span| !span.content.contains('\n')));
+        assert!(lines[0].spans.iter().any(|span| span.style.fg.is_some()));
+        assert!(highlight("select 1;", PLAIN).is_none());
+        assert!(highlight("select 1;", "no-such-language").is_none());
+        assert!(is_known("yaml") && !is_known("no-such-language"));
+        assert_eq!(next_language(None).as_deref(), Some("sh"));
+        assert_eq!(next_language(Some(PLAIN)), None);
+    }
+}
@@ -1,68 +1,614 @@
+use std::fmt;
 use std::path::PathBuf;
 
-use dirs;
-use sled;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sled::transaction::{
+    abort, ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+};
+use sled::Transactional;
+
+use crate::crypto::{Cipher, KdfParams, KDF_ARGON2ID};
+use crate::entry::{now, Entry, HistoryItem, VALUE_FIELD};
+
+/// plaintext sealed into the header to detect a wrong master password
+const CHECK_PLAINTEXT: &[u8] = b"passmr";
+/// associated data of the password check
+const CHECK_AAD: &[u8] = b"";
+/// layout of stored values, recorded in the header:
+/// 1 = json entries and histories, sealed with their key as associated data
+const FORMAT_VERSION: u8 = 1;
+/// number of replaced values kept per key
+const HISTORY_LIMIT: usize = 10;
+
+#[derive(Debug)]
+pub enum KvsError {
+    /// reading or writing the database failed
+    Io(std::io::Error),
+    /// the database or a stored value is damaged
+    Corruption(String),
+    /// a stored key or value is not valid UTF-8
+    Encoding(String),
+    /// the key does not exist
+    NotFound(String),
+    /// the key is taken, e.g. by the target of a rename
+    KeyExists(String),
+    /// the vault has not been created
+    NoSuchVault(String),
+    /// another process holds the database
+    Locked,
+    /// the master password has not been entered yet
+    NotUnlocked,
+    WrongPassword,
+}
+
+impl fmt::Display for KvsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KvsError::Io(e) => write!(f, "i/o error: {}", e),
+            KvsError::Corruption(detail) => write!(f, "database is corrupted: {}", detail),
+            KvsError::Encoding(detail) => write!(f, "invalid UTF-8: {}", detail),
+            KvsError::NotFound(key) => write!(f, "key not found: {}", key),
+            KvsError::KeyExists(key) => write!(f, "key already exists: {}", key),
+            KvsError::NoSuchVault(name) => write!(f, "no such vault: {}", name),
+            KvsError::Locked => write!(f, "database is in use by another passmr process"),
+            KvsError::NotUnlocked => write!(f, "vault is locked"),
+            KvsError::WrongPassword => write!(f, "wrong master password"),
+        }
+    }
+}
+
+impl std::error::Error for KvsError {}
+
+impl From<sled::Error> for KvsError {
+    fn from(e: sled::Error) -> Self {
+        match e {
+            // sled reports a held file lock as a plain io error
+            sled::Error::Io(e) if e.to_string().starts_with("could not acquire lock") => {
+                KvsError::Locked
+            }
+            sled::Error::Io(e) => KvsError::Io(e),
+            sled::Error::Corruption { at, .. } => KvsError::Corruption(format!("at {:?}", at)),
+            e => KvsError::Corruption(e.to_string()),
+        }
+    }
+}
+
+impl From<TransactionError> for KvsError {
+    fn from(e: TransactionError) -> Self {
+        match e {
+            TransactionError::Abort(e) | TransactionError::Storage(e) => e.into(),
+        }
+    }
+}
+
+/// binds a sealed entry to its key, so that it cannot be moved to another key or into the history
+fn entry_aad(key: &[u8]) -> Vec<u8> {
+    [b"entry:".as_slice(), key].concat()
+}
+
+fn history_aad(key: &[u8]) -> Vec<u8> {
+    [b"history:".as_slice(), key].concat()
+}
 
 pub struct Kvs {
     db: sled::Db,
+    /// per-vault salt and password check, kept out of the key-value tree
+    header: sled::Tree,
+    /// previous values of each key, newest first
+    history: sled::Tree,
+    cipher: Option<Cipher>,
 }
 
 impl Kvs {
-    pub fn new(file_path: &PathBuf) -> Result<Kvs, String> {
-        let db = sled::open(file_path).map_err(|e| e.to_string())?;
-        Ok(Kvs { db })
+    pub fn new(file_path: &PathBuf) -> Result<Kvs, KvsError> {
+        let db = sled::open(file_path)?;
+        let header = db.open_tree("header")?;
+        let history = db.open_tree("history")?;
+        Ok(Kvs {
+            db,
+            header,
+            history,
+            cipher: None,
+        })
+    }
+
+    /// whether a master password has already been set for this vault
+    pub fn is_initialized(&self) -> Result<bool, KvsError> {
+        Ok(self.header.contains_key("salt")?)
+    }
+
+    /// the key derivation recorded in the header
+    fn kdf_params(&self) -> Result<KdfParams, KvsError> {
+        match (self.header.get("kdf")?, self.header.get("kdf_params")?) {
+            (Some(kdf), _) if kdf != KDF_ARGON2ID => Err(KvsError::Corruption(format!(
+                "unsupported key derivation: {}",
+                String::from_utf8_lossy(&kdf)
+            ))),
+            (Some(_), Some(params)) => KdfParams::from_bytes(&params)
+                .ok_or_else(|| KvsError::Corruption("invalid key derivation costs".to_string())),
+            _ => Err(KvsError::Corruption(
+                "incomplete key derivation header".to_string(),
+            )),
+        }
+    }
+
+    /// derive the vault key from the master password.
+    /// the first unlock sets the password and encrypts any plaintext values left by older versions.
+    pub fn unlock(&mut self, password: &str) -> Result<(), KvsError> {
+        if let Some(salt) = self.header.get("salt")? {
+            let params = self.kdf_params()?;
+            let cipher = Cipher::derive(password, &salt, &params).map_err(KvsError::Corruption)?;
+            let check = self.header.get("check")?;
+            match check.and_then(|c| cipher.open(&c, CHECK_AAD)) {
+                Some(plaintext) if plaintext == CHECK_PLAINTEXT => {
+                    self.cipher = Some(cipher);
+                    Ok(())
+                }
+                _ => Err(KvsError::WrongPassword),
+            }
+        } else {
+            let salt = Cipher::generate_salt();
+            let params = KdfParams::default();
+            let cipher = Cipher::derive(password, &salt, &params).map_err(KvsError::Corruption)?;
+            let check = cipher.seal(CHECK_PLAINTEXT, CHECK_AAD);
+            let plain_entries = self
+                .db
+                .iter()
+                .map(|item| {
+                    let (key, value) = item?;
+                    let value = String::from_utf8(value.to_vec())
+                        .map_err(|_| KvsError::Encoding(format!("value of {:?}", key)))?;
+                    let json = serde_json::to_vec(&Entry::new(&value))
+                        .map_err(|e| KvsError::Corruption(e.to_string()))?;
+                    Ok((key.clone(), cipher.seal(&json, &entry_aad(&key))))
+                })
+                .collect::<Result<Vec<_>, KvsError>>()?;
+            (&*self.db, &self.header).transaction(|(db, header)| {
+                for (key, sealed) in plain_entries.iter() {
+                    db.insert(key, sealed.as_slice())?;
+                }
+                header.insert("salt", &salt[..])?;
+                header.insert("kdf", KDF_ARGON2ID)?;
+                header.insert("kdf_params", params.to_bytes())?;
+                header.insert("check", check.as_slice())?;
+                header.insert("format", &[FORMAT_VERSION])?;
+                Ok(())
+            })?;
+            // only keep the key once the vault holds what is needed to derive it again
+            self.cipher = Some(cipher);
+            Ok(())
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// forget the vault key; values cannot be read until the next unlock
+    pub fn lock(&mut self) {
+        self.cipher = None;
     }
 
-    pub fn insert(&self, key: &str, value: &str) {
-        self.db.insert(key, value.as_bytes()).unwrap();
+    fn cipher(&self) -> Result<&Cipher, KvsError> {
+        self.cipher.as_ref().ok_or(KvsError::NotUnlocked)
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
+    fn seal_json<T: Serialize>(&self, value: &T, aad: &[u8]) -> Result<Vec<u8>, KvsError> {
+        let json = serde_json::to_vec(value).map_err(|e| KvsError::Corruption(e.to_string()))?;
+        Ok(self.cipher()?.seal(&json, aad))
+    }
+
+    fn open_json<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        sealed: &[u8],
+        aad: &[u8],
+    ) -> Result<T, KvsError> {
+        let json = self
+            .cipher()?
+            .open(sealed, aad)
+            .ok_or_else(|| KvsError::Corruption(format!("cannot decrypt value of {}", key)))?;
+        serde_json::from_slice(&json).map_err(|e| KvsError::Corruption(format!("{}: {}", key, e)))
+    }
+
+    /// move a sealed value to other associated data
+    fn reseal(&self, sealed: &[u8], from: &[u8], to: &[u8]) -> Result<Vec<u8>, KvsError> {
+        let plaintext = self.cipher()?.open(sealed, from).ok_or_else(|| {
+            KvsError::Corruption(format!(
+                "cannot decrypt value of {}",
+                String::from_utf8_lossy(from)
+            ))
+        })?;
+        Ok(self.cipher()?.seal(&plaintext, to))
+    }
+
+    fn seal_entry(&self, key: &[u8], entry: &Entry) -> Result<Vec<u8>, KvsError> {
+        self.seal_json(entry, &entry_aad(key))
+    }
+
+    fn write_entry(&self, key: &str, entry: &Entry) -> Result<(), KvsError> {
         self.db
-            .get(key)
-            .unwrap()
-            .map(|v| std::str::from_utf8(&v).unwrap().to_string())
+            .insert(key, self.seal_entry(key.as_bytes(), entry)?)?;
+        Ok(())
+    }
+
+    /// store `value`, keeping the metadata of an existing entry
+    /// and moving the value it replaces into the history
+    pub fn insert(&self, key: &str, value: &str) -> Result<(), KvsError> {
+        self.insert_with(key, value, |_| {})
+    }
+
+    /// `insert`, also changing the metadata of the entry with `update` in the same write
+    pub fn insert_with(
+        &self,
+        key: &str,
+        value: &str,
+        update: impl FnOnce(&mut Entry),
+    ) -> Result<(), KvsError> {
+        let (mut entry, history) = match self.get_entry(key) {
+            Ok(mut entry) => {
+                let mut history = self.get_history(key)?;
+                if entry.value != value {
+                    history.insert(
+                        0,
+                        HistoryItem {
+                            value: entry.value.clone(),
+                            replaced: now(),
+                        },
+                    );
+                    history.truncate(HISTORY_LIMIT);
+                }
+                entry.set_value(value);
+                (entry, history)
+            }
+            Err(KvsError::NotFound(_)) => (Entry::new(value), vec![]),
+            Err(e) => return Err(e),
+        };
+        update(&mut entry);
+        let sealed_entry = self.seal_entry(key.as_bytes(), &entry)?;
+        let sealed_history = self.seal_json(&history, &history_aad(key.as_bytes()))?;
+        (&*self.db, &self.history).transaction(|(db, history)| {
+            db.insert(key, sealed_entry.as_slice())?;
+            history.insert(key, sealed_history.as_slice())?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// previous values of `key`, newest first
+    pub fn get_history(&self, key: &str) -> Result<Vec<HistoryItem>, KvsError> {
+        match self.history.get(key)? {
+            Some(sealed) => self.open_json(key, &sealed, &history_aad(key.as_bytes())),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// make the `index`-th previous value current again; the replaced value goes into the history
+    pub fn restore(&self, key: &str, index: usize) -> Result<(), KvsError> {
+        let item = self
+            .get_history(key)?
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| KvsError::NotFound(format!("{} (version {})", key, index)))?;
+        self.insert(key, &item.value)
+    }
+
+    pub fn get_entry(&self, key: &str) -> Result<Entry, KvsError> {
+        let sealed = self
+            .db
+            .get(key)?
+            .ok_or_else(|| KvsError::NotFound(key.to_string()))?;
+        self.open_json(key, &sealed, &entry_aad(key.as_bytes()))
+    }
+
+    pub fn contains(&self, key: &str) -> Result<bool, KvsError> {
+        Ok(self.db.contains_key(key)?)
     }
 
-    pub fn get_key_vec(&self) -> Vec<String> {
+    pub fn get(&self, key: &str) -> Result<String, KvsError> {
+        Ok(self.get_entry(key)?.value)
+    }
+
+    /// record that the value was used (copied or printed)
+    pub fn touch(&self, key: &str) -> Result<(), KvsError> {
+        self.update_entry(key, Entry::touch)
+    }
+
+    /// change an existing entry, e.g. its language, masking, kind or tags, in one write
+    pub fn update_entry(&self, key: &str, update: impl FnOnce(&mut Entry)) -> Result<(), KvsError> {
+        let mut entry = self.get_entry(key)?;
+        update(&mut entry);
+        self.write_entry(key, &entry)
+    }
+
+    /// move the entry and history of `from` to `to` in one transaction, re-sealing them for `to`.
+    /// an existing `to` is only replaced, together with its history, when `overwrite` is set
+    pub fn rename(&self, from: &str, to: &str, overwrite: bool) -> Result<(), KvsError> {
+        self.cipher()?;
+        let result = (&*self.db, &self.history).transaction(
+            |(db, history)| -> ConflictableTransactionResult<(), KvsError> {
+                let Some(entry) = db.get(from)? else {
+                    return abort(KvsError::NotFound(from.to_string()));
+                };
+                if from == to {
+                    return Ok(());
+                }
+                if !overwrite && db.get(to)?.is_some() {
+                    return abort(KvsError::KeyExists(to.to_string()));
+                }
+                let (from_bytes, to_bytes) = (from.as_bytes(), to.as_bytes());
+                let entry = self
+                    .reseal(&entry, &entry_aad(from_bytes), &entry_aad(to_bytes))
+                    .map_err(ConflictableTransactionError::Abort)?;
+                db.remove(from)?;
+                db.insert(to, entry)?;
+                match history.remove(from)? {
+                    Some(items) => {
+                        let items = self
+                            .reseal(&items, &history_aad(from_bytes), &history_aad(to_bytes))
+                            .map_err(ConflictableTransactionError::Abort)?;
+                        history.insert(to, items)?
+                    }
+                    None => history.remove(to)?,
+                };
+                Ok(())
+            },
+        );
+        result.map_err(|e| match e {
+            TransactionError::Abort(e) => e,
+            TransactionError::Storage(e) => e.into(),
+        })
+    }
+
+    /// add, replace or, with an empty value, remove a named field.
+    /// `VALUE_FIELD` replaces the main value, keeping the old one in the history.
+    pub fn set_field(&self, key: &str, name: &str, value: &str) -> Result<(), KvsError> {
+        if name == VALUE_FIELD {
+            return self.insert(key, value);
+        }
+        self.update_entry(key, |entry| entry.set_field(name, value))
+    }
+
+    /// every key with its entry, in key order
+    pub fn get_entries(&self) -> Result<Vec<(String, Entry)>, KvsError> {
+        self.get_key_vec()?
+            .into_iter()
+            .map(|key| {
+                let entry = self.get_entry(&key)?;
+                Ok((key, entry))
+            })
+            .collect()
+    }
+
+    pub fn get_key_vec(&self) -> Result<Vec<String>, KvsError> {
         self.db
             .iter()
             .keys()
-            .map(|k| k.unwrap().to_vec())
-            .map(|k| String::from_utf8(k).unwrap())
+            .map(|k| {
+                let k = k?;
+                String::from_utf8(k.to_vec())
+                    .map_err(|_| KvsError::Encoding(format!("key {:?}", k.as_ref())))
+            })
             .collect()
     }
 
-    pub fn delete(&self, key: &str) {
-        self.db.remove(key).unwrap();
+    pub fn delete(&self, key: &str) -> Result<(), KvsError> {
+        let removed = (&*self.db, &self.history).transaction(|(db, history)| {
+            history.remove(key)?;
+            Ok(db.remove(key)?)
+        })?;
+        removed.ok_or_else(|| KvsError::NotFound(key.to_string()))?;
+        Ok(())
     }
-}
 
-impl Default for Kvs {
-    fn default() -> Self {
-        let home_dir = dirs::home_dir().unwrap();
-        let passmr_dir = home_dir.join(".passmr");
-        std::fs::create_dir_all(&passmr_dir).unwrap();
-
-        Kvs::new(&passmr_dir.join("kvs")).unwrap()
+    pub fn flush(&self) -> Result<(), KvsError> {
+        self.db.flush()?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::entry::Kind;
+
+    /// a fresh directory for a test vault, removed when dropped
+    pub(crate) struct TestDir(PathBuf);
+
+    impl TestDir {
+        pub(crate) fn path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// a path of its own, so that tests neither share state between runs
+    /// nor touch the real vaults in $HOME
+    pub(crate) fn test_dir(name: &str) -> TestDir {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        TestDir(std::env::temp_dir().join(format!(
+            "passmr-{}-{}-{}",
+            name,
+            std::process::id(),
+            nanos
+        )))
+    }
+
+    /// an unlocked, empty vault in a `test_dir`
+    pub(crate) fn test_kvs(name: &str) -> (Kvs, TestDir) {
+        let dir = test_dir(name);
+        let mut kvs = Kvs::new(&dir.0).unwrap();
+        kvs.unlock("password").unwrap();
+        (kvs, dir)
+    }
 
     #[test]
     fn test_insert() {
-        let home_dir = dirs::home_dir().unwrap();
-        let passmr_dir = home_dir.join(".passmr");
-        std::fs::create_dir_all(&passmr_dir).unwrap();
-
-        let kvs = Kvs::new(&passmr_dir.join("test_kvs")).unwrap();
+        let (mut kvs, _dir) = test_kvs("insert");
 
         let key = "key";
         let value = "value";
-        kvs.insert(key, value);
-        assert_eq!(kvs.get("key"), Some("value".to_string()));
+        kvs.insert(key, value).unwrap();
+        assert_eq!(kvs.get("key").unwrap(), "value".to_string());
+        assert!(matches!(kvs.get("missing"), Err(KvsError::NotFound(_))));
+        assert!(kvs.contains("key").unwrap());
+        assert!(!kvs.contains("missing").unwrap());
+
+        kvs.touch(key).unwrap();
+        let entry = kvs.get_entry(key).unwrap();
+        assert!(entry.last_accessed.is_some());
+        assert!(entry.access_count > 0);
+
+        kvs.update_entry(key, |entry| entry.language = Some("sql".to_string()))
+            .unwrap();
+        kvs.insert(key, "select 1").unwrap();
+        assert_eq!(kvs.get_entry(key).unwrap().language.as_deref(), Some("sql"));
+        kvs.update_entry(key, |entry| entry.sensitive = false)
+            .unwrap();
+        assert!(!kvs.get_entry(key).unwrap().sensitive);
+        kvs.insert_with(key, "select 3", |entry| entry.kind = Kind::Totp)
+            .unwrap();
+        let entry = kvs.get_entry(key).unwrap();
+        assert_eq!((entry.value.as_str(), entry.kind), ("select 3", Kind::Totp));
+        assert_eq!(kvs.get_history(key).unwrap()[0].value, "select 1");
+        assert!(matches!(
+            kvs.update_entry("missing", |_| {}),
+            Err(KvsError::NotFound(_))
+        ));
+
+        kvs.set_field(key, "username", "alice").unwrap();
+        kvs.set_field(key, VALUE_FIELD, "select 2").unwrap();
+        let entry = kvs.get_entry(key).unwrap();
+        assert_eq!(entry.field("username"), Some("alice"));
+        assert_eq!(entry.value, "select 2");
+
+        kvs.lock();
+        assert!(matches!(kvs.get("key"), Err(KvsError::NotUnlocked)));
+    }
+
+    #[test]
+    fn test_history() {
+        let (kvs, _dir) = test_kvs("history");
+
+        let key = "key";
+        kvs.insert(key, "v1").unwrap();
+        kvs.insert(key, "v2").unwrap();
+        kvs.insert(key, "v3").unwrap();
+        let history = kvs.get_history(key).unwrap();
+        assert_eq!(history[0].value, "v2");
+        assert_eq!(history[1].value, "v1");
+
+        kvs.restore(key, 1).unwrap();
+        assert_eq!(kvs.get(key).unwrap(), "v1");
+        assert_eq!(kvs.get_history(key).unwrap()[0].value, "v3");
+
+        kvs.delete(key).unwrap();
+        assert!(kvs.get_history(key).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rename() {
+        let (kvs, _dir) = test_kvs("rename");
+
+        kvs.insert("old", "v1").unwrap();
+        kvs.insert("old", "v2").unwrap();
+        kvs.insert("other", "x").unwrap();
+        kvs.rename("old", "new", false).unwrap();
+        assert!(matches!(kvs.get("old"), Err(KvsError::NotFound(_))));
+        assert_eq!(kvs.get("new").unwrap(), "v2");
+        assert_eq!(kvs.get_history("new").unwrap()[0].value, "v1");
+        assert!(kvs.get_history("old").unwrap().is_empty());
+
+        assert!(matches!(
+            kvs.rename("new", "other", false),
+            Err(KvsError::KeyExists(_))
+        ));
+        assert_eq!(kvs.get("other").unwrap(), "x");
+        kvs.rename("new", "other", true).unwrap();
+        assert_eq!(kvs.get("other").unwrap(), "v2");
+        assert_eq!(kvs.get_key_vec().unwrap(), vec!["other".to_string()]);
+        assert!(matches!(
+            kvs.rename("missing", "x", false),
+            Err(KvsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_sealed_to_key() {
+        let (kvs, _dir) = test_kvs("sealed_to_key");
+        assert_eq!(kvs.kdf_params().unwrap(), KdfParams::default());
+        assert_eq!(&*kvs.header.get("kdf").unwrap().unwrap(), KDF_ARGON2ID);
+
+        kvs.insert("a", "v1").unwrap();
+        kvs.insert("a", "v2").unwrap();
+        // a value copied to another key, or into the history, does not decrypt there
+        let sealed = kvs.db.get("a").unwrap().unwrap();
+        kvs.db.insert("b", sealed.clone()).unwrap();
+        kvs.history.insert("b", sealed).unwrap();
+        assert!(matches!(kvs.get("b"), Err(KvsError::Corruption(_))));
+        assert!(matches!(kvs.get_history("b"), Err(KvsError::Corruption(_))));
+        kvs.delete("b").unwrap();
+
+        kvs.rename("a", "c", false).unwrap();
+        assert_eq!(kvs.get("c").unwrap(), "v2");
+        assert_eq!(kvs.get_history("c").unwrap()[0].value, "v1");
+    }
+
+    #[test]
+    fn test_encrypt_plaintext() {
+        let dir = test_dir("encrypt_plaintext");
+        // a vault as written before values were encrypted
+        sled::open(&dir.0).unwrap().insert("key", "value").unwrap();
+
+        let mut kvs = Kvs::new(&dir.0).unwrap();
+        assert!(!kvs.is_initialized().unwrap());
+        kvs.unlock("password").unwrap();
+        assert_eq!(kvs.get("key").unwrap(), "value");
+        assert_ne!(&*kvs.db.get("key").unwrap().unwrap(), b"value");
+        assert_eq!(
+            &*kvs.header.get("format").unwrap().unwrap(),
+            &[FORMAT_VERSION]
+        );
+    }
+
+    #[test]
+    fn test_failed_first_unlock() {
+        let dir = test_dir("failed_first_unlock");
+        sled::open(&dir.0)
+            .unwrap()
+            .insert("key", &[0xff][..])
+            .unwrap();
+
+        // a value that cannot be encrypted leaves the vault as it was, and locked
+        let mut kvs = Kvs::new(&dir.0).unwrap();
+        assert!(matches!(kvs.unlock("password"), Err(KvsError::Encoding(_))));
+        assert!(!kvs.is_unlocked());
+        assert!(!kvs.is_initialized().unwrap());
+        assert!(matches!(
+            kvs.insert("new", "value"),
+            Err(KvsError::NotUnlocked)
+        ));
+    }
+
+    #[test]
+    fn test_locked() {
+        let (_kvs, dir) = test_kvs("locked");
+        // sled only tells a held lock apart from other i/o errors by its message
+        assert!(matches!(Kvs::new(dir.path()), Err(KvsError::Locked)));
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let (mut kvs, _dir) = test_kvs("wrong_password");
+        assert!(matches!(kvs.unlock("wrong"), Err(KvsError::WrongPassword)));
     }
 }
@@ -1,39 +1,64 @@
 use std::error::Error;
 
-use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use clap::Parser;
 use ratatui::prelude::*;
 
 use crate::app::{run_app, App};
-use crate::kvs::Kvs;
+use crate::cli::{Cli, Command};
+use crate::config::Config;
+use crate::terminal::TerminalGuard;
 
 mod app;
+mod audit;
+mod cli;
+mod clipboard;
+mod config;
+mod crypto;
+mod editor;
+mod entry;
+mod fuzzy;
+mod generator;
+mod highlight;
 mod kvs;
+mod terminal;
+mod totp;
 mod ui;
+mod vault;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("passmr: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    // generating needs no vault
+    if let Some(Command::Generate(args)) = cli.command {
+        return cli::generate(args);
+    }
+    let vault = cli.vault()?;
+    let creates_vault = cli.creates_vault();
+    let mut kvs = match creates_vault {
+        true => vault.create()?,
+        false => vault.open()?,
+    };
+
+    if let Some(command) = cli.command {
+        return cli::run(command, &mut kvs, creates_vault);
+    }
+
+    let config = Config::load()?;
+
+    // setup terminal; it is restored when the guard is dropped, even on error or panic
+    terminal::install_panic_hook();
+    let _guard = TerminalGuard::enter()?;
+    let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    let mut kvs = Kvs::default();
-    let mut app = App::new();
-    app.sync_key_list(kvs.get_key_vec());
+    let mut app = App::new(config, vault);
     run_app(&mut terminal, &mut app, &mut kvs)?;
 
-    // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-
     Ok(())
 }
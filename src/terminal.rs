@@ -0,0 +1,56 @@
+use std::io;
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+/// puts the terminal into raw mode on the alternate screen and restores it when dropped,
+/// so that `?` exits and panics do not leave the shell unusable
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> io::Result<TerminalGuard> {
+        enable_raw_mode()?;
+        // from here on, an early return drops the guard and undoes what was done so far
+        let guard = TerminalGuard;
+        // bracketed paste delivers a paste as one event instead of key presses,
+        // so a newline in it does not act as Enter
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore();
+    }
+}
+
+fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        Show
+    )
+}
+
+/// restore the terminal before the default hook prints the panic message and backtrace,
+/// otherwise they are written to the alternate screen and lost
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,213 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+
+use crate::entry::now;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// a time-based one-time password generator (RFC 6238)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Totp {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+    /// seconds each code is valid for
+    pub period: u64,
+}
+
+impl Totp {
+    /// a base32 secret as shown by most sites, or an `otpauth://totp/...` URI
+    pub fn parse(value: &str) -> Result<Totp, String> {
+        let value = value.trim();
+        if value.starts_with("otpauth://") {
+            return parse_uri(value);
+        }
+        Ok(Totp {
+            secret: decode_secret(value)?,
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+            period: 30,
+        })
+    }
+
+    /// the code for the time step containing `timestamp`
+    pub fn code_at(&self, timestamp: u64) -> String {
+        let counter = (timestamp / self.period).to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<Hmac<sha1::Sha1>>(&self.secret, &counter),
+            Algorithm::Sha256 => hmac::<Hmac<sha2::Sha256>>(&self.secret, &counter),
+            Algorithm::Sha512 => hmac::<Hmac<sha2::Sha512>>(&self.secret, &counter),
+        };
+        // dynamic truncation, RFC 4226 section 5.3
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let code = binary as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// the current code and the seconds until it changes
+    pub fn current(&self) -> (String, u64) {
+        let now = now();
+        (self.code_at(now), self.period - now % self.period)
+    }
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// base32 as written by humans: any case, spaces or dashes between groups, optional padding
+fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    let normalized = secret
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '='))
+        .collect::<String>()
+        .to_uppercase();
+    let bytes = BASE32_NOPAD
+        .decode(normalized.as_bytes())
+        .map_err(|_| "TOTP secret is not valid base32".to_string())?;
+    if bytes.is_empty() {
+        return Err("TOTP secret is empty".to_string());
+    }
+    Ok(bytes)
+}
+
+/// `otpauth://totp/<label>?secret=...&algorithm=SHA1&digits=6&period=30`
+fn parse_uri(uri: &str) -> Result<Totp, String> {
+    let rest = uri
+        .strip_prefix("otpauth://totp/")
+        .ok_or("only otpauth://totp/ URIs are supported")?;
+    let query = rest.split_once('?').map_or("", |(_, query)| query);
+    let mut totp = Totp {
+        secret: vec![],
+        algorithm: Algorithm::Sha1,
+        digits: 6,
+        period: 30,
+    };
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match name.to_lowercase().as_str() {
+            "secret" => totp.secret = decode_secret(&value)?,
+            "algorithm" => {
+                totp.algorithm = match value.to_uppercase().as_str() {
+                    "SHA1" => Algorithm::Sha1,
+                    "SHA256" => Algorithm::Sha256,
+                    "SHA512" => Algorithm::Sha512,
+                    _ => return Err(format!("unsupported TOTP algorithm: {}", value)),
+                }
+            }
+            "digits" => {
+                totp.digits = value
+                    .parse()
+                    .ok()
+                    .filter(|digits| (6..=9).contains(digits))
+                    .ok_or(format!("invalid TOTP digits: {}", value))?
+            }
+            "period" => {
+                totp.period = value
+                    .parse()
+                    .ok()
+                    .filter(|period| *period > 0)
+                    .ok_or(format!("invalid TOTP period: {}", value))?
+            }
+            // label, issuer and image are for display only
+            _ => {}
+        }
+    }
+    if totp.secret.is_empty() {
+        return Err("otpauth URI has no secret".to_string());
+    }
+    Ok(totp)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// test vectors from RFC 6238 appendix B
+    #[test]
+    fn test_rfc6238() {
+        let totp = |secret: &[u8], algorithm| Totp {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: 8,
+            period: 30,
+        };
+        let sha1 = totp(b"12345678901234567890", Algorithm::Sha1);
+        let sha256 = totp(b"12345678901234567890123456789012", Algorithm::Sha256);
+        let sha512 = totp(
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+            Algorithm::Sha512,
+        );
+        assert_eq!(sha1.code_at(59), "94287082");
+        assert_eq!(sha1.code_at(1111111109), "07081804");
+        assert_eq!(sha1.code_at(1234567890), "89005924");
+        assert_eq!(sha256.code_at(59), "46119246");
+        assert_eq!(sha512.code_at(59), "90693936");
+    }
+
+    #[test]
+    fn test_parse() {
+        // "12345678901234567890" in base32
+        let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let totp = Totp::parse(&secret.to_lowercase()).unwrap();
+        assert_eq!(totp.code_at(59), "287082");
+        assert_eq!(
+            Totp::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(),
+            totp
+        );
+
+        let uri = format!(
+            "otpauth://totp/ACME%20Co:alice?secret={}&issuer=ACME%20Co&digits=8&period=60",
+            secret
+        );
+        let totp = Totp::parse(&uri).unwrap();
+        assert_eq!(totp.period, 60);
+        assert_eq!(totp.code_at(119), "94287082");
+
+        assert!(Totp::parse("not base32!").is_err());
+        assert!(Totp::parse("otpauth://hotp/x?secret=GEZD").is_err());
+        assert!(Totp::parse("otpauth://totp/x?issuer=x").is_err());
+    }
+}
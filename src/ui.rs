@@ -1,27 +1,33 @@
+use std::time::Duration;
+
 use ratatui::prelude::*;
 use ratatui::widgets::*;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::app::StatefulList;
 use crate::app::{App, InputMode};
+use crate::entry::{format_age, Entry, Field, Kind, VALUE_FIELD};
+use crate::fuzzy::Match;
+use crate::highlight;
 use crate::kvs::Kvs;
+use crate::totp::Totp;
 
-fn text_area(text_list: Vec<&str>, frame: &mut Frame, area: &Rect, is_warning: bool) {
+fn text_area(text_list: Vec<&str>, title: &str, frame: &mut Frame, area: &Rect, is_warning: bool) {
     let text = text_list
         .into_iter()
         .map(|t| t.into())
         .collect::<Vec<Line>>();
+    let block = Block::default().borders(Borders::ALL).title(title);
     if is_warning {
         frame.render_widget(
             Paragraph::new(text)
                 .style(Style::default().fg(Color::LightMagenta))
-                .block(Block::default().borders(Borders::ALL)),
+                .block(block),
             *area,
         );
     } else {
-        frame.render_widget(
-            Paragraph::new(text).block(Block::default().borders(Borders::ALL)),
-            *area,
-        );
+        frame.render_widget(Paragraph::new(text).block(block), *area);
     }
 }
 
@@ -40,31 +46,460 @@ fn str_widget_area(
     frame.render_widget(search, *area);
 
     if is_input {
-        frame.set_cursor(area.x + app.cursor_position as u16 + 1, area.y + 1);
+        frame.set_cursor(area.x + app.cursor_column() as u16 + 1, area.y + 1);
     }
 }
 
-fn str_list_widget_area(
-    str_list: Vec<String>,
-    title: &str,
-    frame: &mut Frame,
-    app: &mut App,
-    area: &Rect,
-) {
-    let list_items = str_list
+/// value of the selected entry followed by its metadata, scrolled by `app.viewer_scroll`
+fn entry_widget_area(app: &mut App, kvs: &Kvs, key: &str, frame: &mut Frame, area: &Rect) {
+    let mut language = None;
+    let mut reveal = String::new();
+    let mut text = match kvs.get_entry(key) {
+        Ok(entry) => {
+            let last_used = entry.last_accessed.map_or("never".to_string(), format_age);
+            let meta_style = Style::default().fg(Color::DarkGray);
+            let masked = entry.sensitive && !app.is_revealed();
+            let mut text = if entry.kind == Kind::Totp {
+                language = Some("totp".to_string());
+                if entry.sensitive && !masked {
+                    reveal = reveal_label(app);
+                }
+                totp_lines(&entry.value, masked)
+            } else if masked {
+                vec![Line::styled(hidden_text(&entry.value), meta_style)]
+            } else {
+                language = entry
+                    .language
+                    .as_deref()
+                    .or_else(|| highlight::detect(&entry.value))
+                    .filter(|language| *language != highlight::PLAIN)
+                    .map(str::to_string);
+                if entry.sensitive {
+                    reveal = reveal_label(app);
+                }
+                value_lines(&entry.value, language.as_deref())
+            };
+            if !entry.fields.is_empty() {
+                text.push(Line::from(""));
+            }
+            for field in &entry.fields {
+                text.push(Line::from(vec![
+                    Span::styled(
+                        format!("{}: ", field.name),
+                        Style::default().fg(Color::LightBlue),
+                    ),
+                    Span::raw(field_preview(&entry, &field.name, masked)),
+                ]));
+            }
+            text.extend([
+                Line::from(""),
+                Line::styled(
+                    format!("created:   {}", format_age(entry.created)),
+                    meta_style,
+                ),
+                Line::styled(
+                    format!("updated:   {}", format_age(entry.updated)),
+                    meta_style,
+                ),
+                Line::styled(
+                    format!("last used: {} ({} times)", last_used, entry.access_count),
+                    meta_style,
+                ),
+            ]);
+            if !entry.tags.is_empty() {
+                text.push(Line::styled(
+                    format!("tags:      {}", entry.tags.join(", ")),
+                    meta_style,
+                ));
+            }
+            text
+        }
+        Err(e) => vec![Line::from(format!("<{}>", e))],
+    };
+
+    // never scroll past the last line
+    let height = area.height.saturating_sub(2) as usize;
+    let max_scroll = text.len().saturating_sub(height);
+    app.viewer_scroll = app.viewer_scroll.min(max_scroll);
+    let mut title = if max_scroll > 0 {
+        format!(
+            "Value (lines {}-{} of {})",
+            app.viewer_scroll + 1,
+            app.viewer_scroll + height,
+            text.len()
+        )
+    } else {
+        "Value".to_string()
+    };
+    if let Some(language) = language {
+        title = format!("{} [{}]", title, language);
+    }
+    title.push_str(&reveal);
+    text.drain(..app.viewer_scroll);
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL).title(title)),
+        *area,
+    );
+}
+
+/// the current code of a TOTP entry and a bar of the seconds it stays valid for
+fn totp_lines(value: &str, masked: bool) -> Vec<Line<'static>> {
+    const BAR_WIDTH: u64 = 30;
+    let totp = match Totp::parse(value) {
+        Ok(totp) => totp,
+        Err(e) => {
+            return vec![Line::styled(
+                format!("<{}>", e),
+                Style::default().fg(Color::LightRed),
+            )]
+        }
+    };
+    let (code, remaining) = totp.current();
+    let code = match masked {
+        true => "*".repeat(code.len()),
+        false => code,
+    };
+    let (first, second) = code.split_at(code.len() / 2);
+    let filled = (remaining * BAR_WIDTH).div_ceil(totp.period).min(BAR_WIDTH);
+    let bar_color = match remaining {
+        0..=5 => Color::LightRed,
+        _ => Color::LightGreen,
+    };
+    let hint = match masked {
+        true => "  (press 'v' to show)",
+        false => "",
+    };
+    vec![
+        Line::from(vec![
+            Span::styled(
+                format!("{} {}", first, second),
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(hint, Style::default().fg(Color::DarkGray)),
+        ]),
+        Line::from(vec![
+            Span::styled("█".repeat(filled as usize), Style::default().fg(bar_color)),
+            Span::styled(
+                "░".repeat((BAR_WIDTH - filled) as usize),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::raw(format!(" {}s", remaining)),
+        ]),
+    ]
+}
+
+/// one line for a field of `entry`: its first line, and a note of how many lines follow
+fn field_preview(entry: &Entry, name: &str, masked: bool) -> String {
+    let value = entry.field(name).unwrap_or_default();
+    if masked && (name == VALUE_FIELD || !Field::is_public(name)) {
+        return hidden_text(value);
+    }
+    if entry.kind == Kind::Totp && name == VALUE_FIELD {
+        return "(TOTP secret, 'Enter' copies the current code)".to_string();
+    }
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or_default().to_string();
+    match lines.count() {
+        0 => first,
+        more => format!("{} (+{} lines)", first, more),
+    }
+}
+
+/// what is shown instead of a masked value
+fn hidden_text(value: &str) -> String {
+    match value.lines().count() {
+        0 | 1 => "******** (hidden, press 'v' to show)".to_string(),
+        lines => format!("******** ({} lines hidden, press 'v' to show)", lines),
+    }
+}
+
+/// each character as a '*' of the same width, so that the cursor still lines up
+fn mask(value: &str) -> String {
+    value
+        .graphemes(true)
+        .map(|g| match g {
+            "\n" => g.to_string(),
+            g => "*".repeat(g.width()),
+        })
+        .collect()
+}
+
+/// whole seconds left, rounded up so that a countdown reaches 0 exactly when it runs out
+fn secs_left(remaining: Duration) -> u128 {
+    remaining.as_millis().div_ceil(1000)
+}
+
+/// a bordered list whose selected item is marked with ">>"
+fn selectable_list<'a>(items: Vec<ListItem<'a>>, title: impl Into<block::Title<'a>>) -> List<'a> {
+    List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ")
+}
+
+/// title suffix for a sensitive value that is currently shown
+fn reveal_label(app: &App) -> String {
+    match app.reveal_remaining() {
+        Some(remaining) => format!(" [shown, hidden in {}s]", secs_left(remaining)),
+        None => " [shown]".to_string(),
+    }
+}
+
+/// a value with syntax highlighting if `language` is known, plain white text otherwise
+fn value_lines(value: &str, language: Option<&str>) -> Vec<Line<'static>> {
+    language
+        .and_then(|language| highlight::highlight(value, language))
+        .unwrap_or_else(|| {
+            value
+                .lines()
+                .map(|line| Line::from(line.to_string()))
+                .collect()
+        })
+}
+
+/// multi-line value input, scrolled so that the cursor stays visible
+fn value_editor_area(app: &mut App, title: &str, frame: &mut Frame, area: &Rect) {
+    let line = app.value_input.cursor_line() as u16;
+    let column = app.value_input.cursor_width() as u16;
+    let height = area.height.saturating_sub(2).max(1);
+    let width = area.width.saturating_sub(2).max(1);
+    let (mut top, mut left) = app.value_scroll;
+    top = top.clamp((line + 1).saturating_sub(height), line);
+    left = left.clamp((column + 1).saturating_sub(width), column);
+    app.value_scroll = (top, left);
+
+    let value = app.value_input.as_str();
+    let text = if app.value_sensitive && !app.is_revealed() {
+        value_lines(&mask(value), None)
+    } else {
+        let language = app.language.as_deref().or_else(|| highlight::detect(value));
+        value_lines(value, language)
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((top, left)),
+        *area,
+    );
+    frame.set_cursor(area.x + 1 + column - left, area.y + 1 + line - top);
+}
+
+/// a key with the characters matching the search highlighted, followed by its tags
+fn match_line(key_match: &Match, tags: Option<&Vec<String>>) -> Line<'static> {
+    let highlight = Style::default()
+        .fg(Color::LightCyan)
+        .add_modifier(Modifier::BOLD);
+    let spans = key_match
+        .text
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if key_match.positions.contains(&i) {
+                Span::styled(c.to_string(), highlight)
+            } else {
+                Span::raw(c.to_string())
+            }
+        })
+        .collect::<Vec<Span>>();
+    let mut line = Line::from(spans);
+    if let Some(tags) = tags {
+        line.spans.push(Span::styled(
+            format!("  #{}", tags.join(" #")),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    line
+}
+
+/// previous values of the selected key, newest first
+fn history_list_widget_area(app: &mut App, masked: bool, frame: &mut Frame, area: &Rect) {
+    let list_items = app
+        .history_list
+        .items
         .iter()
-        .map(|i| {
-            let lines = vec![Line::from(i.as_str())];
-            ListItem::new(lines)
+        .map(|item| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<16}", format_age(item.replaced)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(match masked {
+                    true => "********",
+                    false => item.value.lines().next().unwrap_or_default(),
+                }),
+                Span::styled(
+                    match item.value.lines().count() {
+                        0 | 1 => String::new(),
+                        lines => format!(" (+{} lines)", lines - 1),
+                    },
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
         })
         .collect::<Vec<ListItem>>();
-    let ui_key_list =
-        List::new(list_items).block(Block::default().borders(Borders::ALL).title(title));
-    frame.render_widget(ui_key_list, *area);
+    let title = match list_items.len() {
+        0 => "History (empty)",
+        _ => "History",
+    };
+    let ui_history_list = selectable_list(list_items, title);
+    frame.render_stateful_widget(ui_history_list, *area, &mut app.history_list.state);
+}
+
+/// vaults on disk, with the open one marked
+fn vault_list_widget_area(app: &mut App, frame: &mut Frame, area: &Rect) {
+    let list_items = app
+        .vault_list
+        .items
+        .iter()
+        .map(|name| {
+            if *name == app.vault.name {
+                ListItem::new(Line::from(vec![
+                    Span::raw(name.as_str()),
+                    Span::styled(" (open)", Style::default().fg(Color::DarkGray)),
+                ]))
+            } else {
+                ListItem::new(Line::from(name.as_str()))
+            }
+        })
+        .collect::<Vec<ListItem>>();
+    let ui_vault_list = selectable_list(list_items, "Vault");
+    frame.render_stateful_widget(ui_vault_list, *area, &mut app.vault_list.state);
+}
+
+/// findings of the audit, key first
+fn audit_list_widget_area(app: &mut App, frame: &mut Frame, area: &Rect) {
+    let width = app
+        .audit_list
+        .items
+        .iter()
+        .map(|finding| finding.key.width())
+        .max()
+        .unwrap_or_default();
+    let list_items = app
+        .audit_list
+        .items
+        .iter()
+        .map(|finding| {
+            let padding = " ".repeat(width - finding.key.width() + 2);
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{}{}", finding.key, padding)),
+                Span::styled(finding.describe(), Style::default().fg(Color::LightRed)),
+            ]))
+        })
+        .collect::<Vec<ListItem>>();
+    let title = match list_items.len() {
+        0 => "Audit (no findings)".to_string(),
+        1 => "Audit (1 finding)".to_string(),
+        n => format!("Audit ({} findings)", n),
+    };
+    let ui_audit_list = selectable_list(list_items, title);
+    frame.render_stateful_widget(ui_audit_list, *area, &mut app.audit_list.state);
+}
+
+/// fields of the selected entry with their values, masked like the entry
+fn field_list_widget_area(app: &mut App, kvs: &Kvs, key: &str, frame: &mut Frame, area: &Rect) {
+    let entry = match kvs.get_entry(key) {
+        Ok(entry) => entry,
+        Err(e) => {
+            let message = format!("<{}>", e);
+            return str_widget_area(message, "Fields", frame, app, area, false);
+        }
+    };
+    let masked = entry.sensitive && !app.is_revealed();
+    let width = app
+        .field_list
+        .items
+        .iter()
+        .map(|name| name.width())
+        .max()
+        .unwrap_or_default();
+    let list_items = app
+        .field_list
+        .items
+        .iter()
+        .map(|name| {
+            let padding = " ".repeat(width - name.width() + 2);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}{}", name, padding),
+                    Style::default().fg(Color::LightBlue),
+                ),
+                Span::raw(field_preview(&entry, name, masked)),
+            ]))
+        })
+        .collect::<Vec<ListItem>>();
+    let mut title = "Fields".to_string();
+    if entry.sensitive && !masked {
+        title.push_str(&reveal_label(app));
+    }
+    let ui_field_list = selectable_list(list_items, title);
+    frame.render_stateful_widget(ui_field_list, *area, &mut app.field_list.state);
+}
+
+/// every tag with its number of keys; the tags filtered by are marked
+fn tag_list_widget_area(app: &mut App, frame: &mut Frame, area: &Rect) {
+    let active = app.active_tags();
+    let list_items = app
+        .tag_list
+        .items
+        .iter()
+        .map(|(tag, count)| {
+            let line = format!("{} ({})", tag, count);
+            match active.contains(tag) {
+                true => ListItem::new(format!("* {}", line)).style(
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                false => ListItem::new(format!("  {}", line)),
+            }
+        })
+        .collect::<Vec<ListItem>>();
+    let focused = matches!(app.mode, InputMode::Tags);
+    let title = match focused {
+        true => "Tags",
+        false => "Tags (Ctrl-t)",
+    };
+    let ui_tag_list = selectable_list(list_items, title);
+    // the selection is only shown while the sidebar has the focus
+    match focused {
+        true => frame.render_stateful_widget(ui_tag_list, *area, &mut app.tag_list.state),
+        false => frame.render_widget(ui_tag_list, *area),
+    }
+}
+
+/// the key list, with the tag sidebar on its right if any key is tagged
+fn key_list_with_tags_area(app: &mut App, frame: &mut Frame, area: &Rect) {
+    if app.tag_list.items.is_empty() {
+        return stateful_list_widget_area(app.get_statefule_list(), "Key", frame, app, area);
+    }
+    let width = app
+        .tag_list
+        .items
+        .iter()
+        .map(|(tag, count)| tag.width() + count.to_string().len() + 8)
+        .max()
+        .unwrap_or_default()
+        .clamp(16, 32) as u16;
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(width)])
+        .split(*area);
+    stateful_list_widget_area(app.get_statefule_list(), "Key", frame, app, &columns[0]);
+    tag_list_widget_area(app, frame, &columns[1]);
 }
 
 fn stateful_list_widget_area(
-    stateful_list: StatefulList<String>,
+    stateful_list: StatefulList<Match>,
     title: &str,
     frame: &mut Frame,
     app: &mut App,
@@ -73,131 +508,409 @@ fn stateful_list_widget_area(
     let list_items = stateful_list
         .items
         .iter()
-        .map(|i| {
-            let lines = vec![Line::from(i.as_str())];
-            ListItem::new(lines)
-        })
+        .map(|i| ListItem::new(match_line(i, app.key_tags.get(&i.text))))
         .collect::<Vec<ListItem>>();
-    let ui_key_list = List::new(list_items)
-        .block(Block::default().borders(Borders::ALL).title(title))
-        .highlight_style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol(">> ");
+    let ui_key_list = selectable_list(list_items, title);
 
     let mut_key_list = app.get_mut_stateful_key_list();
     frame.render_stateful_widget(ui_key_list, *area, &mut mut_key_list.state);
 }
 
+/// the value of `key` unless it is masked, or why it could not be read
+fn value_or_error(app: &App, kvs: &Kvs, key: &str) -> String {
+    match kvs.get_entry(key) {
+        Ok(entry) if entry.sensitive && !app.is_revealed() => hidden_text(&entry.value),
+        Ok(entry) => entry.value,
+        Err(e) => format!("<{}>", e),
+    }
+}
+
+fn status_area(app: &App, frame: &mut Frame, area: &Rect) {
+    if let Some(message) = &app.error_message {
+        frame.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::LightRed)),
+            *area,
+        );
+    } else if let Some(message) = &app.info_message {
+        frame.render_widget(
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::LightBlue)),
+            *area,
+        );
+    } else if let Some(remaining) = app.clipboard.remaining() {
+        let secs = secs_left(remaining);
+        let text = match app.clipboard.notice() {
+            Some(value) => format!(
+                "no clipboard available, value: {} (hidden in {}s)",
+                value, secs
+            ),
+            None => format!(
+                "copied via {}, clearing in {}s",
+                app.clipboard.provider_name().unwrap_or_default(),
+                secs
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(Style::default().fg(Color::LightGreen)),
+            *area,
+        );
+    }
+}
+
 pub fn ui(frame: &mut Frame, app: &mut App, kvs: &mut Kvs) {
+    let outer_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(frame.size());
+    status_area(app, frame, &outer_chunks[1]);
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(9),
             Constraint::Length(3),
-            Constraint::Length(30),
-            Constraint::Length(1),
+            Constraint::Percentage(40),
+            Constraint::Min(0),
         ])
-        .split(frame.size());
+        .split(outer_chunks[0]);
+    // everything below the input, for the key list or a multi-line value
+    let body = chunks[2].union(chunks[3]);
 
+    let lock_message = app.lock_message.clone().unwrap_or_default();
     let mode_text = match app.mode {
+        InputMode::Locked if kvs.is_initialized().unwrap_or(true) => vec![
+            "passmr is locked:",
+            "- type the master password and press 'Enter' to unlock",
+            "- press 'Tab' to switch vault",
+            "- press 'Esc' to quit",
+            lock_message.as_str(),
+        ],
+        InputMode::Locked => vec![
+            "New vault:",
+            "- type a master password and press 'Enter' to set it",
+            "- press 'Tab' to switch vault",
+            "- press 'Esc' to quit",
+            lock_message.as_str(),
+        ],
         InputMode::Home => vec![
             "=========================",
             "   Welcome to passmr!   ",
             "=========================",
             "- press 's' to search",
             "- press 'a' to add",
+            "- press 'v' to switch vault, 'u' to audit the values",
             "- press 'q' to quit",
         ],
         InputMode::Search => vec![
             "Search Mode:",
-            "- press 'Enter' to get value",
+            "- type to filter, press '↑'/'↓' or 'Ctrl-n'/'Ctrl-p' to select",
+            "- press 'Enter' to copy to clipboard",
+            "- press 'Tab' to edit, show previous values or delete",
+            "- press 'Ctrl-t' to filter by tag, or type 'tag:name'",
             "- press 'Esc' to exit search mode",
         ],
+        InputMode::Tags => vec![
+            "Tag Mode:",
+            "- press 'Enter' to filter by the selected tag (again to stop)",
+            "- press 'Space' to add it to the filter and stay here",
+            "- press 'j'/'k' to move down/up",
+            "- press 'Esc' to go back to search mode",
+        ],
+        InputMode::EditTags => vec![
+            "Edit Tags Mode:",
+            "- type tags separated by commas or spaces, e.g. 'aws, prod'",
+            "- press 'Enter' to save",
+            "- press 'Esc' to go back to select mode",
+        ],
         InputMode::Select => vec![
             "Select Mode:",
-            "- press 'Enter' to copy to clipboard",
-            "- press 'j' to move down",
-            "- press 'k' to move up",
-            "- press 'e' to edit value",
-            "- press 'd' to delete key-value",
-            "- press 'Esc' to exit select mode",
+            "- press 'Enter' to copy to clipboard, 't' to use the value as a TOTP secret",
+            "- press 'j'/'k' to move down/up, 'J'/'K' to scroll the value",
+            "- press 'e' to edit value, 'f' to copy/edit fields, 'r' to rename the key",
+            "- press 'h' to show previous values, '#' to edit tags, 'd' to delete key-value",
+            "- press 'v' to show/hide the value, 'm' to turn masking on/off",
+            "- press 'Esc' to go back to search mode",
+        ],
+        InputMode::History => vec![
+            "History Mode:",
+            "- press 'Enter' to restore the selected value",
+            "- press 'j'/'k' to move down/up, 'v' to show/hide the values",
+            "- press 'Esc' to go back to select mode",
+        ],
+        InputMode::Delete => vec![
+            "press 'y' to delete",
+            "press 'v' to show/hide the value",
+            "press 'Esc' to cancel",
         ],
-        InputMode::Delete => vec!["press 'y' to delete", "press 'Esc' to cancel"],
         InputMode::Edit => vec![
             "Edit Mode:",
             "- press 'Enter' to save",
+            "- press 'Alt-Enter' or 'Ctrl-j' to insert a line break",
+            "- press 'Ctrl-l' to change the highlighting language",
+            "- press 'Ctrl-r' to show/hide the value",
+            "- press 'Ctrl-g'/'Alt-g' to generate a password/passphrase",
             "- press 'Esc' to exit edit mode",
         ],
+        InputMode::Rename => vec![
+            "Rename Mode:",
+            "- type the new key and press 'Enter' to rename",
+            "- press 'Esc' to go back to select mode",
+        ],
+        InputMode::RenameConfirm => vec![
+            "the new key already exists:",
+            "press 'y' to overwrite it (its value and history are lost)",
+            "press 'Esc' to choose another key",
+        ],
         InputMode::AddKey => vec![
             "Add Key-Value Mode:",
             "- press 'Enter' to add value",
             "- press 'Esc' to exit add key-value mode",
         ],
+        InputMode::AddExists => vec![
+            "the key already exists:",
+            "press 'y' to replace its value (the current one is kept in its history)",
+            "press 'e' to edit the existing entry instead",
+            "press 'Esc' to choose another key",
+        ],
         InputMode::AddValue => vec![
             "Add Key-Value Mode:",
             "- press 'Enter' to save",
+            "- press 'Alt-Enter' or 'Ctrl-j' to insert a line break",
+            "- press 'Ctrl-l' to change the highlighting language",
+            "- press 'Ctrl-r' to show/hide the value",
+            "- press 'Ctrl-g'/'Alt-g' to generate a password/passphrase",
             "- press 'Esc' to exit add key-value mode",
         ],
+        InputMode::Vault => vec![
+            "Vault Mode:",
+            "- press 'Enter' to open the selected vault",
+            "- press 'j'/'k' to move down/up",
+            "- press 'n' to create a new vault",
+            "- press 'Esc' to go back",
+        ],
+        InputMode::Audit => vec![
+            "Audit Mode:",
+            "- press 'Enter' to edit the value of the selected key",
+            "- press 'j'/'k' to move down/up",
+            "- press 'Esc' to go back",
+        ],
+        InputMode::Fields => vec![
+            "Fields Mode:",
+            "- press 'Enter' to copy the selected field to clipboard",
+            "- press 'j'/'k' to move down/up, 'v' to show/hide the values",
+            "- press 'a' to add a field, 'e' to edit the selected one",
+            "- save a field empty to remove it",
+            "- press 'Esc' to go back to select mode",
+        ],
+        InputMode::FieldName => vec![
+            "Add Field Mode:",
+            "- type a name such as 'username' or 'url' and press 'Enter'",
+            "- press 'Esc' to go back to fields mode",
+        ],
+        InputMode::FieldValue => vec![
+            "Edit Field Mode:",
+            "- press 'Enter' to save, an empty value removes the field",
+            "- press 'Alt-Enter' or 'Ctrl-j' to insert a line break",
+            "- press 'Ctrl-r' to show/hide the value",
+            "- press 'Esc' to go back to fields mode",
+        ],
+        InputMode::NewVault => vec![
+            "New Vault Mode:",
+            "- type a name and press 'Enter' to create and open it",
+            "- press 'Esc' to go back to vault mode",
+        ],
+    };
+    let vault_title = format!("vault: {}", app.vault.name);
+    let detected = highlight::detect(app.value_input.as_str());
+    let language = match (app.language.as_deref(), detected) {
+        (Some(language), _) => language.to_string(),
+        (None, Some(detected)) => format!("auto: {}", detected),
+        (None, None) => "auto".to_string(),
+    };
+    let mut value_title = match app.value_input.line_count() {
+        1 => format!("Value [{}]", language),
+        lines => format!("Value ({} lines) [{}]", lines, language),
     };
+    if app.value_sensitive {
+        match app.is_revealed() {
+            true => value_title.push_str(&reveal_label(app)),
+            false => value_title.push_str(" [hidden]"),
+        }
+    }
     match app.mode {
-        InputMode::Delete => text_area(mode_text, frame, &chunks[0], true),
-        _ => text_area(mode_text, frame, &chunks[0], false),
+        InputMode::Delete | InputMode::RenameConfirm | InputMode::AddExists => {
+            text_area(mode_text, &vault_title, frame, &chunks[0], true)
+        }
+        InputMode::Locked if app.lock_message.is_some() => {
+            text_area(mode_text, &vault_title, frame, &chunks[0], true)
+        }
+        _ => text_area(mode_text, &vault_title, frame, &chunks[0], false),
     };
 
     match app.mode {
+        InputMode::Locked => {
+            str_widget_area(
+                "*".repeat(app.password_input.grapheme_count()),
+                "Master Password",
+                frame,
+                app,
+                &chunks[1],
+                true,
+            );
+        }
         InputMode::Home => {}
         InputMode::Search => {
             str_widget_area(
-                app.search_input.clone(),
+                app.search_input.as_str().to_string(),
                 "Search",
                 frame,
                 app,
                 &chunks[1],
                 true,
             );
-            str_list_widget_area(app.get_search_key_list(), "Key", frame, app, &chunks[2]);
+            key_list_with_tags_area(app, frame, &body);
+        }
+        InputMode::Tags => {
+            str_widget_area(
+                app.search_input.as_str().to_string(),
+                "Search",
+                frame,
+                app,
+                &chunks[1],
+                false,
+            );
+            key_list_with_tags_area(app, frame, &body);
+        }
+        InputMode::EditTags => {
+            str_widget_area(
+                app.key_input.as_str().to_string(),
+                "Tags",
+                frame,
+                app,
+                &chunks[1],
+                true,
+            );
+            if let Some(key) = app.get_selected_key() {
+                entry_widget_area(app, kvs, key.as_str(), frame, &body);
+            }
         }
         InputMode::Select => {
             stateful_list_widget_area(app.get_statefule_list(), "Key", frame, app, &chunks[2]);
             if let Some(key) = app.get_selected_key() {
-                let value = kvs.get(key.as_str()).unwrap();
-                str_widget_area(value.clone(), "Value", frame, app, &chunks[3], false);
+                entry_widget_area(app, kvs, key.as_str(), frame, &chunks[3]);
+            }
+        }
+        InputMode::History => {
+            let mut masked = !app.is_revealed();
+            if let Some(key) = app.get_selected_key() {
+                masked &= kvs.get_entry(&key).map_or(true, |entry| entry.sensitive);
+                str_widget_area(key, "Key", frame, app, &chunks[1], false);
             }
+            history_list_widget_area(app, masked, frame, &chunks[2]);
         }
         InputMode::Edit => {
             if let Some(key) = app.get_selected_key() {
-                str_widget_area(key.clone(), "Key", frame, app, &chunks[2], false);
-                str_widget_area(
-                    app.value_input.clone(),
-                    "Value",
-                    frame,
-                    app,
-                    &chunks[3],
-                    true,
-                );
+                str_widget_area(key.clone(), "Key", frame, app, &chunks[1], false);
+                value_editor_area(app, &value_title, frame, &body);
             }
         }
         InputMode::Delete => {
             if let Some(key) = app.get_selected_key() {
                 str_widget_area(key.clone(), "Key", frame, app, &chunks[2], false);
-                let value = kvs.get(key.as_str()).unwrap();
+                let value = value_or_error(app, kvs, key.as_str());
                 str_widget_area(value.clone(), "Value", frame, app, &chunks[3], false);
             }
         }
+        InputMode::Rename => {
+            str_widget_area(
+                app.key_input.as_str().to_string(),
+                "New Key",
+                frame,
+                app,
+                &chunks[1],
+                true,
+            );
+            if let Some(key) = app.get_selected_key() {
+                entry_widget_area(app, kvs, key.as_str(), frame, &body);
+            }
+        }
+        InputMode::RenameConfirm => {
+            // show what would be overwritten
+            let new_key = app.key_input.as_str().to_string();
+            str_widget_area(new_key.clone(), "New Key", frame, app, &chunks[1], false);
+            entry_widget_area(app, kvs, new_key.as_str(), frame, &body);
+        }
         InputMode::AddKey => {
-            str_widget_area(app.key_input.clone(), "Key", frame, app, &chunks[1], true);
+            str_widget_area(
+                app.key_input.as_str().to_string(),
+                "Key",
+                frame,
+                app,
+                &chunks[1],
+                true,
+            );
+        }
+        InputMode::AddExists => {
+            let key = app.key_input.as_str().to_string();
+            str_widget_area(key.clone(), "Key", frame, app, &chunks[1], false);
+            entry_widget_area(app, kvs, key.as_str(), frame, &body);
         }
         InputMode::AddValue => {
-            str_widget_area(app.key_input.clone(), "Key", frame, app, &chunks[1], false);
             str_widget_area(
-                app.value_input.clone(),
-                "Value",
+                app.key_input.as_str().to_string(),
+                "Key",
+                frame,
+                app,
+                &chunks[1],
+                false,
+            );
+            value_editor_area(app, &value_title, frame, &body);
+        }
+        InputMode::Vault => {
+            vault_list_widget_area(app, frame, &chunks[2]);
+        }
+        InputMode::Audit => {
+            audit_list_widget_area(app, frame, &body);
+        }
+        InputMode::Fields => {
+            if let Some(key) = app.get_selected_key() {
+                str_widget_area(key.clone(), "Key", frame, app, &chunks[1], false);
+                field_list_widget_area(app, kvs, key.as_str(), frame, &body);
+            }
+        }
+        InputMode::FieldName => {
+            str_widget_area(
+                app.key_input.as_str().to_string(),
+                "Field Name",
+                frame,
+                app,
+                &chunks[1],
+                true,
+            );
+            if let Some(key) = app.get_selected_key() {
+                field_list_widget_area(app, kvs, key.as_str(), frame, &body);
+            }
+        }
+        InputMode::FieldValue => {
+            if let Some(key) = app.get_selected_key() {
+                str_widget_area(key.clone(), "Key", frame, app, &chunks[1], false);
+                let field = app.editing_field.clone().unwrap_or_default();
+                let mut title = format!("Field: {}", field);
+                if app.value_sensitive {
+                    match app.is_revealed() {
+                        true => title.push_str(&reveal_label(app)),
+                        false => title.push_str(" [hidden]"),
+                    }
+                }
+                value_editor_area(app, &title, frame, &body);
+            }
+        }
+        InputMode::NewVault => {
+            str_widget_area(
+                app.vault_input.as_str().to_string(),
+                "Vault Name",
                 frame,
                 app,
-                &chunks[2],
+                &chunks[1],
                 true,
             );
         }
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::passmr_dir;
+use crate::kvs::{Kvs, KvsError};
+
+/// name of the vault at `$HOME/.passmr/kvs`, which predates named vaults
+pub const DEFAULT_VAULT: &str = "default";
+
+/// a key-value store on disk and the name it is shown under
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vault {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// `$HOME/.passmr/vaults`, where named vaults other than the default live
+fn vaults_dir() -> std::io::Result<PathBuf> {
+    Ok(passmr_dir()?.join("vaults"))
+}
+
+impl Vault {
+    /// `default` is `$HOME/.passmr/kvs`, any other name `$HOME/.passmr/vaults/<name>`
+    pub fn named(name: &str) -> Result<Vault, String> {
+        Vault::named_in(&passmr_dir().map_err(|e| e.to_string())?, name)
+    }
+
+    /// `named`, with `dir` in place of `$HOME/.passmr`
+    fn named_in(dir: &Path, name: &str) -> Result<Vault, String> {
+        let path = if name == DEFAULT_VAULT {
+            dir.join("kvs")
+        } else {
+            validate_name(name)?;
+            dir.join("vaults").join(name)
+        };
+        Ok(Vault {
+            name: name.to_string(),
+            path,
+        })
+    }
+
+    /// a vault outside `$HOME/.passmr`, named after its path
+    pub fn at(path: PathBuf) -> Vault {
+        Vault {
+            name: path.display().to_string(),
+            path,
+        }
+    }
+
+    /// the default vault, if it was created, followed by the named vaults, sorted
+    pub fn list() -> std::io::Result<Vec<String>> {
+        let mut names = vec![];
+        match std::fs::read_dir(vaults_dir()?) {
+            Ok(entries) => {
+                for entry in entries {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if entry.file_type()?.is_dir()
+                        && name != DEFAULT_VAULT
+                        && validate_name(&name).is_ok()
+                    {
+                        names.push(name);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        names.sort();
+        if Vault::named(DEFAULT_VAULT).is_ok_and(|vault| vault.exists()) {
+            names.insert(0, DEFAULT_VAULT.to_string());
+        }
+        Ok(names)
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// open an existing vault; vaults are only created explicitly,
+    /// so that a mistyped name does not leave an empty vault behind
+    pub fn open(&self) -> Result<Kvs, KvsError> {
+        if !self.exists() {
+            return Err(KvsError::NoSuchVault(self.name.clone()));
+        }
+        Kvs::new(&self.path)
+    }
+
+    /// create the vault if it does not exist yet, and open it
+    pub fn create(&self) -> Result<Kvs, KvsError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(KvsError::Io)?;
+        }
+        Kvs::new(&self.path)
+    }
+}
+
+/// names become directory names, so keep them to a safe set of characters
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid vault name {:?}: use letters, digits, '-', '_' and '.'",
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kvs::tests::test_dir;
+
+    #[test]
+    fn test_vault_names() {
+        let dir = Path::new("passmr");
+        let named = |name| Vault::named_in(dir, name);
+        assert_eq!(named(DEFAULT_VAULT).unwrap().path, dir.join("kvs"));
+        assert_eq!(named("work").unwrap().path, dir.join("vaults/work"));
+        assert!(named("").is_err());
+        assert!(named("..").is_err());
+        assert!(named("a/b").is_err());
+    }
+
+    #[test]
+    fn test_open_needs_create() {
+        let dir = test_dir("vault");
+        let vault = Vault::at(dir.path().join("vaults/work"));
+        assert!(matches!(vault.open(), Err(KvsError::NoSuchVault(_))));
+        assert!(!vault.exists());
+        drop(vault.create().unwrap());
+        assert!(vault.open().is_ok());
+    }
+}